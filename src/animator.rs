@@ -1,28 +1,304 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::{Duration, HashMap}};
 
-use crate::{GameState, player::abilities::Cooldown};
+use crate::{
+    enemies::Enemy,
+    layers,
+    player::{abilities::{Cooldown, CooldownText}, Player},
+    settings::Choice,
+    GameState,
+};
 
 pub struct AnimatorPlugin;
 
 impl Plugin for AnimatorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(animate_sprite)
+        app.add_event::<AnimationFinished>()
+            .add_system(animate_sprite)
             .add_system(damage_flash)
-            .add_system(ability_cooldown);
+            .add_system(ability_cooldown)
+            .insert_resource(HighContrast(false))
+            .add_system(toggle_outlines)
+            .add_system(sync_outlines)
+            .insert_resource(ParticleQuality::default())
+            .add_event::<LifetimeExpired>()
+            .add_system(expire_lifetimes)
+            .add_system(simple_motion)
+            .add_system(update_droplets)
+            .insert_resource(YSortEnabled(false))
+            .add_system(y_sort);
     }
 }
 
+/// How many particles effects spawn. Lower settings help cluttered scenes and slow devices.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParticleQuality {
+    Off,
+    Low,
+    #[default]
+    High,
+}
+
+impl ParticleQuality {
+    /// Scales the number of particles an effect would spawn at high quality.
+    pub fn scale(&self, count: usize) -> usize {
+        match self {
+            Self::Off => 0,
+            Self::Low => (count + 1) / 2,
+            Self::High => count,
+        }
+    }
+}
+
+impl Choice for ParticleQuality {
+    const ALL: &'static [Self] = &[Self::Off, Self::Low, Self::High];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Low => "Low",
+            Self::High => "High",
+        }
+    }
+}
+
+/// Draws y-sorted entities lower on the screen in front of those higher up.
+#[derive(Resource)]
+pub struct YSortEnabled(pub bool);
+
+/// An entity whose z comes from its y position when y-sorting is enabled.
+#[derive(Component)]
+pub struct YSorted {
+    /// The z band the entity goes back to when y-sorting is disabled.
+    pub band: f32,
+}
+
+/// The height, in pixels, y positions are spread over in the y-sorted range.
+const Y_SORT_HEIGHT: f32 = 4096.;
+
+fn y_sort(enabled: Res<YSortEnabled>, mut query: Query<(&YSorted, &mut Transform)>) {
+    for (sorted, mut transform) in query.iter_mut() {
+        let z = if enabled.0 {
+            let t = (transform.translation.y / Y_SORT_HEIGHT).clamp(0., 1.);
+            layers::Y_SORTED_MAX - (layers::Y_SORTED_MAX - layers::Y_SORTED_MIN) * t
+        } else {
+            sorted.band
+        };
+
+        // Avoid triggering change detection every frame
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    }
+}
+
+/// Despawns the entity once the timer finishes.
+#[derive(Component)]
+pub struct Lifetime(pub Timer);
+
+impl Lifetime {
+    pub fn from_seconds(duration: f32) -> Self {
+        Self(Timer::from_seconds(duration, TimerMode::Once))
+    }
+}
+
+/// Sent when an entity's [`Lifetime`] runs out and it's despawned.
+pub struct LifetimeExpired(pub Entity);
+
+fn expire_lifetimes(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Lifetime)>,
+    mut expired: EventWriter<LifetimeExpired>,
+    time: Res<Time>,
+    state: Res<GameState>,
+) {
+    if *state != GameState::Gameplay {
+        return;
+    }
+
+    for (entity, mut lifetime) in query.iter_mut() {
+        lifetime.0.tick(time.delta());
+
+        if lifetime.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            expired.send(LifetimeExpired(entity));
+        }
+    }
+}
+
+/// Moves a cosmetic entity in a straight line or an arc, without a physics body.
 #[derive(Component, Default)]
-pub struct AnimationIndices {
+pub struct SimpleMotion {
+    pub velocity: Vec2,
+    /// How fast the velocity falls, in pixels per second squared.
+    pub gravity: f32,
+}
+
+fn simple_motion(
+    mut query: Query<(&mut SimpleMotion, &mut Transform)>,
+    time: Res<Time>,
+    state: Res<GameState>,
+) {
+    // The start menu has its own cosmetics drifting behind it
+    if !matches!(*state, GameState::Gameplay | GameState::StartMenu) {
+        return;
+    }
+
+    for (mut motion, mut transform) in query.iter_mut() {
+        motion.velocity.y -= motion.gravity * time.delta_seconds();
+        transform.translation += (motion.velocity * time.delta_seconds()).extend(0.);
+    }
+}
+
+/// A small particle that flies out from an effect, falls, and fades away over its [`Lifetime`].
+#[derive(Component)]
+pub struct Droplet;
+
+pub const DROPLET_GRAVITY: f32 = 400.;
+
+fn update_droplets(mut droplets: Query<(&Lifetime, &mut Sprite), With<Droplet>>) {
+    for (lifetime, mut sprite) in droplets.iter_mut() {
+        sprite.color.set_a(lifetime.0.percent_left());
+    }
+}
+
+/// A run of frames in a spritesheet, played at its own speed.
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationClip {
     pub first: usize,
     pub last: usize,
+    pub fps: f32,
+    /// Whether the clip starts over after the last frame, rather than holding it.
+    pub looping: bool,
+    /// The clip to switch to once this one finishes, if it doesn't loop.
+    pub next: Option<&'static str>,
+}
+
+impl AnimationClip {
+    /// A looping clip.
+    pub fn new(first: usize, last: usize, fps: f32) -> Self {
+        Self {
+            first,
+            last,
+            fps,
+            looping: true,
+            next: None,
+        }
+    }
+
+    /// Makes the clip play once and hold its last frame.
+    pub fn once(mut self) -> Self {
+        self.looping = false;
+        self
+    }
+
+    /// Makes the clip play once and then switch to the clip called `next`.
+    pub fn then(mut self, next: &'static str) -> Self {
+        self.looping = false;
+        self.next = Some(next);
+        self
+    }
+}
+
+/// The animations an entity can play, by name, and which one is playing.
+#[derive(Component)]
+pub struct AnimationStateMachine {
+    clips: HashMap<&'static str, AnimationClip>,
+    state: &'static str,
+    /// Frames since the start of the current clip.
+    frame: usize,
+    timer: Timer,
 }
 
-#[derive(Component, Deref, DerefMut, Default)]
-pub struct AnimationTimer(pub Timer);
+impl AnimationStateMachine {
+    /// Starts out playing `clip`. More clips can be added with [`Self::with_clip`].
+    pub fn new(state: &'static str, clip: AnimationClip) -> Self {
+        Self {
+            clips: HashMap::from_iter([(state, clip)]),
+            state,
+            frame: 0,
+            timer: Self::frame_timer(clip),
+        }
+    }
+
+    pub fn with_clip(mut self, name: &'static str, clip: AnimationClip) -> Self {
+        self.clips.insert(name, clip);
+        self
+    }
+
+    fn frame_timer(clip: AnimationClip) -> Timer {
+        Timer::from_seconds(1. / clip.fps, TimerMode::Repeating)
+    }
+
+    pub fn state(&self) -> &'static str {
+        self.state
+    }
+
+    /// Switches to another clip, starting it from its first frame.
+    /// Does nothing if the clip is already playing or doesn't exist, so it's safe to call every frame.
+    pub fn set_state(&mut self, name: &'static str) {
+        if self.state == name {
+            return;
+        }
+        let Some(clip) = self.clips.get(name).copied() else { return };
+
+        self.state = name;
+        self.frame = 0;
+        self.timer = Self::frame_timer(clip);
+    }
+
+    /// The spritesheet index to show.
+    pub fn index(&self) -> usize {
+        self.clips.get(self.state).map_or(0, |clip| clip.first + self.frame)
+    }
+
+    /// Whether a clip that doesn't loop has reached its last frame.
+    pub fn finished(&self) -> bool {
+        self.clips
+            .get(self.state)
+            .map_or(true, |clip| !clip.looping && clip.first + self.frame >= clip.last)
+    }
 
+    /// Advances the animation, returning the clip that finished if one that doesn't loop reached its last frame.
+    pub fn tick(&mut self, delta: Duration) -> Option<&'static str> {
+        let clip = self.clips.get(self.state).copied()?;
+
+        self.timer.tick(delta);
+
+        for _ in 0..self.timer.times_finished_this_tick() {
+            if clip.first + self.frame < clip.last {
+                self.frame += 1;
+
+                if !clip.looping && clip.first + self.frame == clip.last {
+                    let finished = self.state;
+                    if let Some(next) = clip.next {
+                        self.set_state(next);
+                    }
+                    return Some(finished);
+                }
+            } else if clip.looping {
+                self.frame = 0;
+            }
+        }
+
+        None
+    }
+}
+
+/// Sent when an animation clip that doesn't loop reaches its last frame.
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub state: &'static str,
+}
+
+/// Runs once, the first time a clip of the entity's animation finishes.
 #[derive(Component)]
-pub struct Destruct;
+pub struct OnAnimationEnd(Option<Box<dyn FnOnce(&mut World) + Send + Sync>>);
+
+impl OnAnimationEnd {
+    pub fn new(callback: impl FnOnce(&mut World) + Send + Sync + 'static) -> Self {
+        Self(Some(Box::new(callback)))
+    }
+}
 
 fn animate_sprite(
     mut commands: Commands,
@@ -30,24 +306,26 @@ fn animate_sprite(
     state: Res<GameState>,
     mut query: Query<(
         Entity,
-        &AnimationIndices,
-        &mut AnimationTimer,
+        &mut AnimationStateMachine,
         &mut TextureAtlasSprite,
-        Option<&Destruct>,
+        Option<&mut OnAnimationEnd>,
     )>,
+    mut finished: EventWriter<AnimationFinished>,
 ) {
     if *state == GameState::Gameplay {
-        for (entity, indices, mut timer, mut sprite, destruct) in &mut query {
-            timer.tick(time.delta());
-            if timer.just_finished() {
-                sprite.index = if sprite.index == indices.last {
-                    if destruct.is_some() {
-                        commands.entity(entity).despawn();
-                    }
-                    indices.first
-                } else {
-                    sprite.index + 1
-                };
+        for (entity, mut machine, mut sprite, on_end) in &mut query {
+            if let Some(clip) = machine.tick(time.delta()) {
+                finished.send(AnimationFinished { entity, state: clip });
+
+                if let Some(callback) = on_end.and_then(|mut on_end| on_end.0.take()) {
+                    commands.add(move |world: &mut World| callback(world));
+                    commands.entity(entity).remove::<OnAnimationEnd>();
+                }
+            }
+
+            let index = machine.index();
+            if sprite.index != index {
+                sprite.index = index;
             }
         }
     }
@@ -56,6 +334,10 @@ fn animate_sprite(
 #[derive(Component)]
 pub struct DamageFlash(Timer);
 
+/// The color a sprite goes back to once a [`DamageFlash`] ends, for sprites that are tinted.
+#[derive(Component, Clone, Copy)]
+pub struct BaseColor(pub Color);
+
 impl Default for DamageFlash {
     fn default() -> Self {
         Self(Timer::from_seconds(0.1, TimerMode::Once))
@@ -65,12 +347,12 @@ impl Default for DamageFlash {
 fn damage_flash(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(Entity, &mut DamageFlash, &mut TextureAtlasSprite)>,
+    mut query: Query<(Entity, &mut DamageFlash, &mut TextureAtlasSprite, Option<&BaseColor>)>,
 ) {
-    for (entity, mut flash, mut sprite) in query.iter_mut() {
+    for (entity, mut flash, mut sprite, base_color) in query.iter_mut() {
         flash.0.tick(time.delta());
         if flash.0.finished() {
-            sprite.color = Color::WHITE;
+            sprite.color = base_color.map_or(Color::WHITE, |base_color| base_color.0);
             commands.entity(entity).remove::<DamageFlash>();
         } else {
             sprite.color = Color::RED;
@@ -81,17 +363,108 @@ fn damage_flash(
 fn ability_cooldown(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(Entity, &mut Cooldown, &mut TextureAtlasSprite)>,
+    mut query: Query<(Entity, &mut Cooldown, &mut TextureAtlasSprite, Option<&Children>)>,
+    mut texts: Query<&mut Text, With<CooldownText>>,
+    game_state: Res<GameState>,
 ) {
-    for (entity, mut cooldown, mut sprite) in query.iter_mut() {
+    // Kept in step with the cooldowns themselves, which don't run while paused
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    for (entity, mut cooldown, mut sprite, children) in query.iter_mut() {
         cooldown.0.tick(time.delta());
 
         let frame = (((cooldown.0.elapsed_secs() / cooldown.0.duration().as_secs_f32()) * 17.0) as usize).min(16);
 
         sprite.index = frame;
 
+        // Whole seconds, until the last second where it counts down in tenths
+        let remaining = cooldown.0.remaining_secs();
+        let label = if remaining >= 1.0 {
+            format!("{}", remaining.ceil())
+        } else {
+            format!("{:.1}", remaining)
+        };
+
+        for child in children.into_iter().flatten() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+
         if cooldown.0.finished() {
-            commands.entity(entity).despawn();
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Draws outlines around the player and enemies so they stand out from the background.
+#[derive(Resource)]
+pub struct HighContrast(pub bool);
+
+/// Sprite colors multiply the texture, so the outline copies come out as a yellow-tinted version of the sprite
+/// rather than a flat silhouette. Dark pixels stay dark, but the edges still stand out against the background.
+const OUTLINE_COLOR: Color = Color::YELLOW;
+
+/// A copy of the parent's sprite, drawn behind it and offset by a pixel to form an outline.
+#[derive(Component)]
+struct Outline;
+
+#[derive(Component)]
+struct Outlined;
+
+fn toggle_outlines(
+    mut commands: Commands,
+    high_contrast: Res<HighContrast>,
+    targets: Query<
+        (Entity, &Handle<TextureAtlas>, Option<&Outlined>),
+        Or<(With<Player>, With<Enemy>)>,
+    >,
+    outlines: Query<Entity, With<Outline>>,
+) {
+    if high_contrast.0 {
+        for (entity, texture_atlas, outlined) in targets.iter() {
+            if outlined.is_some() {
+                continue;
+            }
+
+            commands.entity(entity).insert(Outlined).with_children(|parent| {
+                for offset in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y] {
+                    parent.spawn((
+                        Outline,
+                        SpriteSheetBundle {
+                            texture_atlas: texture_atlas.clone(),
+                            sprite: TextureAtlasSprite {
+                                color: OUTLINE_COLOR,
+                                ..default()
+                            },
+                            transform: Transform::from_translation(offset.extend(-0.1)),
+                            ..default()
+                        },
+                    ));
+                }
+            });
+        }
+    } else if high_contrast.is_changed() {
+        for outline in outlines.iter() {
+            commands.entity(outline).despawn();
+        }
+        for (entity, _, outlined) in targets.iter() {
+            if outlined.is_some() {
+                commands.entity(entity).remove::<Outlined>();
+            }
         }
     }
 }
+
+fn sync_outlines(
+    parents: Query<&TextureAtlasSprite, Without<Outline>>,
+    mut outlines: Query<(&Parent, &mut TextureAtlasSprite), With<Outline>>,
+) {
+    for (parent, mut sprite) in outlines.iter_mut() {
+        let Ok(parent_sprite) = parents.get(**parent) else { continue };
+        sprite.index = parent_sprite.index;
+        sprite.flip_x = parent_sprite.flip_x;
+    }
+}
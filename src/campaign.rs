@@ -0,0 +1,214 @@
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    utils::{Duration, HashMap},
+    window::WindowCloseRequested,
+};
+use bevy_ecs_ldtk::LevelSelection;
+
+use crate::{
+    player::{DamageTaken, MaxHealth, PlayerHealth},
+    score::Score,
+    storage,
+    unlocks::PracticeRun,
+    GameMode, GameState, GameTimer,
+};
+
+const SAVE_FILE: &str = "campaign";
+const RECORDS_FILE: &str = "records";
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SavedRun(RunSave::load()))
+            .insert_resource(BestTime::load())
+            .add_system(save_run)
+            .add_system(clear_finished_run)
+            .add_system(record_best_time);
+    }
+}
+
+/// A run in progress, saved so it can be continued in a later session.
+#[derive(Clone, Copy)]
+pub struct RunSave {
+    pub level: usize,
+    pub health: i32,
+    /// Seconds left on the [`GameTimer`].
+    pub remaining: f32,
+    /// Seconds played so far, for timing the run.
+    pub elapsed: f32,
+    pub score: i32,
+}
+
+impl RunSave {
+    fn save(&self) {
+        let mut values = HashMap::new();
+        values.insert("level".to_owned(), self.level.to_string());
+        values.insert("health".to_owned(), self.health.to_string());
+        values.insert("remaining".to_owned(), self.remaining.to_string());
+        values.insert("elapsed".to_owned(), self.elapsed.to_string());
+        values.insert("score".to_owned(), self.score.to_string());
+
+        storage::save(SAVE_FILE, &values);
+    }
+
+    /// Loads the saved run, or nothing if there isn't one or it can't be read.
+    fn load() -> Option<Self> {
+        let values = storage::load(SAVE_FILE);
+
+        let run = Self {
+            level: values.get("level")?.parse().ok()?,
+            health: values.get("health")?.parse().ok()?,
+            remaining: values.get("remaining")?.parse().ok()?,
+            // Saves from before runs were timed start the clock over
+            elapsed: values.get("elapsed").and_then(|value| value.parse().ok()).unwrap_or_default(),
+            score: values.get("score").and_then(|value| value.parse().ok()).unwrap_or_default(),
+        };
+
+        // A run that was already over can't be continued, and neither can a clock that's been mangled
+        let valid_times = run.remaining.is_finite() && run.elapsed.is_finite();
+        (valid_times && run.health > 0 && run.remaining > 0.).then_some(run)
+    }
+
+    fn clear() {
+        storage::save(SAVE_FILE, &HashMap::new());
+    }
+
+    /// Puts the run's resources back, so entering gameplay spawns the saved level.
+    pub fn restore(&self, commands: &mut Commands, max_health: &MaxHealth) {
+        commands.insert_resource(LevelSelection::Index(self.level));
+        commands.insert_resource(PlayerHealth(self.health.min(max_health.0)));
+        // Saves don't record it, so a run continued at full health is taken as unhurt
+        commands.insert_resource(DamageTaken(self.health < max_health.0));
+        commands.insert_resource(GameTimer::resume(
+            Duration::try_from_secs_f32(self.elapsed).unwrap_or_default(),
+            Duration::try_from_secs_f32(self.remaining).unwrap_or(Duration::from_secs(crate::GAME_TIME)),
+        ));
+        commands.insert_resource(Score(self.score));
+    }
+}
+
+/// The run the start menu can continue, if there's one saved.
+#[derive(Resource, Default)]
+pub struct SavedRun(pub Option<RunSave>);
+
+impl SavedRun {
+    /// Forgets the saved run, for when a fresh one starts.
+    pub fn clear(&mut self) {
+        RunSave::clear();
+        self.0 = None;
+    }
+}
+
+/// Saves the run when a level starts, when the game is paused and when the window closes.
+/// Browsers don't reliably say when a tab is closing, so the first two keep the save recent.
+fn save_run(
+    game_state: Res<GameState>,
+    level_selection: Res<LevelSelection>,
+    health: Res<PlayerHealth>,
+    timer: Res<GameTimer>,
+    mut exit: EventReader<AppExit>,
+    mut close: EventReader<WindowCloseRequested>,
+    mut saved: ResMut<SavedRun>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+) {
+    // Wave mode runs are endless, so there's nothing to continue
+    if *mode != GameMode::Campaign {
+        return;
+    }
+
+    let quitting = exit.iter().count() > 0 || close.iter().count() > 0;
+    let paused = game_state.is_changed() && *game_state == GameState::Paused;
+
+    if !game_state.in_run() || !(quitting || paused || level_selection.is_changed()) {
+        return;
+    }
+    let LevelSelection::Index(level) = *level_selection else { return };
+
+    let run = RunSave {
+        level,
+        health: health.0,
+        remaining: timer.remaining_secs(),
+        elapsed: timer.elapsed().as_secs_f32(),
+        score: score.0,
+    };
+    run.save();
+    saved.0 = Some(run);
+}
+
+fn clear_finished_run(game_state: Res<GameState>, mut saved: ResMut<SavedRun>) {
+    if game_state.is_changed()
+        && matches!(*game_state, GameState::GameOver | GameState::WinScreen)
+    {
+        saved.clear();
+    }
+}
+
+/// The fastest the campaign has been beaten, kept between sessions.
+#[derive(Resource, Default)]
+pub struct BestTime {
+    pub best: Option<Duration>,
+    /// Whether the run that just ended set the record, for the win screen to point out.
+    pub new_record: bool,
+}
+
+impl BestTime {
+    fn save(&self) {
+        let mut values = HashMap::new();
+        if let Some(best) = self.best {
+            values.insert("best_time".to_owned(), best.as_secs_f32().to_string());
+        }
+
+        storage::save(RECORDS_FILE, &values);
+    }
+
+    /// Loads the record, treating one that's missing or can't be read as no record at all.
+    fn load() -> Self {
+        let values = storage::load(RECORDS_FILE);
+
+        let best = values
+            .get("best_time")
+            .and_then(|value| value.parse().ok())
+            .and_then(|seconds| Duration::try_from_secs_f32(seconds).ok())
+            .filter(|best| !best.is_zero());
+
+        Self {
+            best,
+            new_record: false,
+        }
+    }
+}
+
+/// Formats a time as minutes and seconds, the same way the HUD timer does.
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{:0>2}:{:0>2}", seconds / 60, seconds % 60)
+}
+
+pub fn record_best_time(
+    game_state: Res<GameState>,
+    mode: Res<GameMode>,
+    practice: Res<PracticeRun>,
+    timer: Res<GameTimer>,
+    mut best_time: ResMut<BestTime>,
+) {
+    if !game_state.is_changed() || *game_state != GameState::WinScreen {
+        return;
+    }
+    best_time.new_record = false;
+
+    // Restarted levels would make for an unfair time
+    if *mode != GameMode::Campaign || practice.0 {
+        return;
+    }
+
+    // The timer only runs during gameplay, so what's elapsed on it is how long the run took
+    let time = timer.elapsed();
+    if best_time.best.map_or(true, |best| time < best) {
+        best_time.best = Some(time);
+        best_time.new_record = true;
+        best_time.save();
+    }
+}
@@ -1,13 +1,17 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::player::{Player, PlayerPhysics};
+use crate::{
+    player::{Player, PlayerPhysics},
+    sound::{LevelMusic, MusicAudition},
+    world::ShowCollisions,
+};
 
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(RapierDebugRenderPlugin::default())
+        app.insert_resource(ShowCollisions(true))
             .add_startup_system(setup_debug_info);
 
         let asset_server = app.world.resource::<AssetServer>();
@@ -24,6 +28,7 @@ impl Plugin for DebugPlugin {
         app.add_system(debug_position);
         app.add_system(debug_velocity);
         app.add_system(debug_physics);
+        app.add_system(cycle_music);
     }
 }
 
@@ -39,6 +44,9 @@ struct DebugVelocity;
 #[derive(Component)]
 struct DebugPhysics;
 
+#[derive(Component)]
+struct DebugMusic;
+
 fn setup_debug_info(mut commands: Commands, text_style: Res<DebugTextStyle>) {
     let DebugTextStyle(ref text_style) = *text_style;
 
@@ -118,6 +126,17 @@ fn setup_debug_info(mut commands: Commands, text_style: Res<DebugTextStyle>) {
                     DebugPhysics,
                 ));
             });
+
+            parent.spawn(NodeBundle::default()).with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section("", text_style.clone()).with_style(Style {
+                        margin: UiRect::vertical(Val::Px(5.0)),
+                        ..default()
+                    }),
+                    Label,
+                    DebugMusic,
+                ));
+            });
         });
 }
 
@@ -168,3 +187,59 @@ fn debug_physics(
 
     *debug = Text::from_section(format!("{:?}", physics), text_style.clone());
 }
+
+/// Steps through every level's music with F7, to audition tracks without playing to them.
+/// Shift+F7 goes back to the level's own music.
+fn cycle_music(
+    keys: Res<Input<KeyCode>>,
+    level_music: Option<Res<LevelMusic>>,
+    audition: Option<ResMut<MusicAudition>>,
+    asset_server: Res<AssetServer>,
+    text_style: Res<DebugTextStyle>,
+    mut debug: Query<&mut Text, With<DebugMusic>>,
+) {
+    // Audio is left out in safe mode
+    let (Some(level_music), Some(mut audition)) = (level_music, audition) else { return };
+    let Ok(mut debug) = debug.get_single_mut() else { return };
+
+    if !keys.just_pressed(KeyCode::F7) {
+        return;
+    }
+
+    if keys.pressed(KeyCode::LShift) {
+        audition.0 = None;
+        debug.sections.clear();
+        return;
+    }
+
+    let mut levels: Vec<_> = level_music.tracks.keys().copied().collect();
+    levels.sort();
+
+    // Levels can share a track, which only needs hearing once
+    let mut tracks = vec![level_music.default.clone()];
+    for level in levels {
+        let track = level_music.track(level);
+        if !tracks.contains(track) {
+            tracks.push(track.clone());
+        }
+    }
+
+    let next = audition
+        .0
+        .as_ref()
+        .and_then(|current| tracks.iter().position(|track| track == current))
+        .map_or(0, |index| (index + 1) % tracks.len());
+    let track = tracks[next].clone();
+
+    let name = asset_server
+        .get_handle_path(&track)
+        .map_or_else(|| "Unknown".to_owned(), |path| path.path().display().to_string());
+
+    let DebugTextStyle(ref text_style) = *text_style;
+
+    *debug = Text::from_section(
+        format!("Music ({}/{}): {name} [Shift+F7 to Stop]", next + 1, tracks.len()),
+        text_style.clone(),
+    );
+    audition.0 = Some(track);
+}
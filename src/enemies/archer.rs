@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::LdtkEntity};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    animator::*,
+    layers,
+    player::{
+        abilities::{DamageEffect, SpeedEffect},
+        Player,
+    },
+    world::{field_value, HitStop},
+    GameState,
+};
+
+use super::{EnemyBundle, EnemyDamageActivator, EnemyGroundSensor, EnemyPhysics, Health, Stunned};
+
+/// How close, in pixels, the player has to be for an archer to notice them.
+const AGGRO_RANGE: f32 = 320.;
+/// How close, in pixels, an archer walks up to the player before stopping to shoot.
+const SHOOTING_RANGE: f32 = 160.;
+const ARCHER_SPEED: f32 = 600.;
+const ARROW_SPEED: f32 = 300.;
+/// Cleans up arrows that somehow never hit anything.
+const ARROW_LIFETIME: f32 = 3.;
+
+#[derive(Component)]
+pub struct Archer {
+    /// Health change applied to the player by each arrow.
+    pub damage: i32,
+    pub fire_timer: Timer,
+}
+
+impl Default for Archer {
+    fn default() -> Self {
+        Self {
+            damage: -1,
+            fire_timer: Timer::from_seconds(1.5, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct ArcherBundle {
+    pub archer: Archer,
+    pub enemy: EnemyBundle,
+    pub health: Health,
+    pub animation: AnimationStateMachine,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub collider: Collider,
+    pub mass: ColliderMassProperties,
+}
+
+impl LdtkEntity for ArcherBundle {
+    fn bundle_entity(
+        entity_instance: &bevy_ecs_ldtk::EntityInstance,
+        _: &bevy_ecs_ldtk::prelude::LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&bevy_ecs_ldtk::prelude::TilesetDefinition>,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let texture = asset_server.load("images/enemies/archer_spritesheet.png");
+        let texture_atlas = TextureAtlas::from_grid(texture, Vec2::new(32., 64.), 3, 2, None, None);
+        let texture_atlas = texture_atlases.add(texture_atlas);
+
+        let mut archer = Archer::default();
+
+        if let Some(FieldValue::Int(Some(damage))) =
+            field_value(&entity_instance.field_instances, "Damage")
+        {
+            archer.damage = *damage;
+        }
+
+        Self {
+            archer,
+            enemy: EnemyBundle::default(),
+            health: Health::new(2),
+            animation: AnimationStateMachine::new("walk", AnimationClip::new(0, 4, 12.))
+                .with_clip("hurt", AnimationClip::new(5, 5, 1.)),
+            texture_atlas,
+            collider: Collider::capsule_y(20., 11.),
+            mass: ColliderMassProperties::Density(0.1),
+        }
+    }
+}
+
+pub fn on_archer_spawn(mut commands: Commands, mut archers: Query<(Entity, &mut Transform), Added<Archer>>) {
+    for (entity, mut transform) in archers.iter_mut() {
+        transform.translation.z = layers::ENEMIES;
+        commands.entity(entity).insert(YSorted { band: layers::ENEMIES });
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                EnemyGroundSensor,
+                Sensor,
+                Collider::cuboid(8., 8.),
+                ActiveEvents::COLLISION_EVENTS,
+                ActiveHooks::MODIFY_SOLVER_CONTACTS,
+                CollisionGroups::new(
+                    Group::GROUP_3,
+                    Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_4 | Group::GROUP_8,
+                ),
+                TransformBundle {
+                    local: Transform::from_xyz(0., -26., 0.),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+/// Fired by archers. Hurts the player through its [`EnemyDamageActivator`] and breaks on whatever it hits.
+#[derive(Component)]
+pub struct Arrow;
+
+/// Walks towards the player until they're in range, then stands and shoots while it can see them.
+pub fn ai(
+    mut commands: Commands,
+    mut archers: Query<
+        (
+            Entity,
+            &mut Velocity,
+            &mut Archer,
+            &Transform,
+            &EnemyPhysics,
+            Option<&SpeedEffect>,
+            Option<&DamageEffect>,
+        ),
+        Without<Stunned>,
+    >,
+    player: Query<(Entity, &Transform), (With<Player>, Without<Archer>)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    hit_stop: Res<HitStop>,
+) {
+    if *game_state != GameState::Gameplay || hit_stop.active() {
+        return;
+    }
+    let Ok((player, player_transform)) = player.get_single() else { return };
+
+    for (entity, mut velocity, mut archer, transform, physics, speed_effect, damage_effect) in archers.iter_mut() {
+        let offset = (player_transform.translation - transform.translation).truncate();
+        let distance = offset.length();
+
+        if distance > AGGRO_RANGE {
+            continue;
+        }
+
+        let speed_multiplier = speed_effect.map_or(1.0, |effect| effect.multiplier);
+
+        if distance > SHOOTING_RANGE {
+            if physics.grounded {
+                velocity.linvel.x += offset.x.signum() * ARCHER_SPEED * speed_multiplier * time.delta_seconds();
+            }
+            continue;
+        }
+
+        // Slowed archers draw their bows slower too, and frozen ones don't shoot at all
+        archer.fire_timer.tick(time.delta().mul_f32(speed_multiplier));
+        if !archer.fire_timer.just_finished() {
+            continue;
+        }
+
+        let direction = offset.normalize_or_zero();
+        let origin = transform.translation.truncate();
+
+        let filter = QueryFilter::new()
+            .exclude_collider(entity)
+            .exclude_sensors()
+            .groups(CollisionGroups::new(Group::GROUP_4, Group::GROUP_1 | Group::GROUP_2));
+
+        let in_sight = matches!(
+            rapier_context.cast_ray(origin, direction, distance, true, filter),
+            Some((hit, _)) if hit == player
+        );
+
+        if !in_sight {
+            continue;
+        }
+
+        let mut arrow = commands.spawn((
+            Arrow,
+            EnemyDamageActivator(archer.damage),
+            RigidBody::Dynamic,
+            Collider::cuboid(6., 1.),
+            ColliderMassProperties::Density(0.1),
+            // A group of their own, so they only meet the walls and the player's damage sensor,
+            // and pass through each other, other enemies and pickups
+            CollisionGroups::new(Group::GROUP_10, Group::GROUP_1 | Group::GROUP_5),
+            ActiveEvents::COLLISION_EVENTS,
+            Velocity::linear(direction * ARROW_SPEED),
+            Lifetime::from_seconds(ARROW_LIFETIME),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.6, 0.5, 0.4),
+                    custom_size: Some(Vec2::new(12., 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(origin.extend(layers::EFFECTS))
+                    .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x))),
+                ..default()
+            },
+        ));
+
+        // Arrows carry the archer's damage multiplier, the same as its melee contact would
+        if let Some(effect) = damage_effect {
+            arrow.insert(DamageEffect {
+                multiplier: effect.multiplier,
+            });
+        }
+    }
+}
+
+pub fn arrow_hits(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    arrows: Query<(), With<Arrow>>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(a, b, _) = collision_event else { continue };
+
+        let (arrow, other) = if arrows.contains(*a) {
+            (*a, *b)
+        } else if arrows.contains(*b) {
+            (*b, *a)
+        } else {
+            continue;
+        };
+
+        commands.entity(arrow).despawn_recursive();
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::LdtkEntity};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    animator::*,
+    layers,
+    player::{abilities::SpeedEffect, Player},
+    world::{field_value, HitStop},
+    GameState,
+};
+
+use super::{EnemyBundle, EnemyDamageActivator, Flying, Health, Stunned};
+
+/// How close, in pixels, the player has to be for a bat to start chasing them.
+const AGGRO_RANGE: f32 = 256.;
+const BAT_SPEED: f32 = 80.;
+/// How far, in pixels, a bat bobs above and below its path.
+const BOB_HEIGHT: f32 = 12.;
+/// How many times a second a bat bobs up and down.
+const BOB_RATE: f32 = 1.5;
+/// How quickly a bat turns towards where it wants to go.
+const BAT_STEERING: f32 = 6.;
+
+#[derive(Component)]
+pub struct Bat {
+    /// Health change applied to the player on contact.
+    pub damage: i32,
+    /// How far through its bob the bat is, so bats don't all bob in step.
+    pub phase: f32,
+}
+
+impl Default for Bat {
+    fn default() -> Self {
+        Self {
+            damage: -1,
+            phase: 0.,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct BatBundle {
+    pub bat: Bat,
+    pub enemy: EnemyBundle,
+    pub health: Health,
+    pub flying: Flying,
+    pub animation: AnimationStateMachine,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub collider: Collider,
+    pub mass: ColliderMassProperties,
+}
+
+impl LdtkEntity for BatBundle {
+    fn bundle_entity(
+        entity_instance: &bevy_ecs_ldtk::EntityInstance,
+        _: &bevy_ecs_ldtk::prelude::LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&bevy_ecs_ldtk::prelude::TilesetDefinition>,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let texture = asset_server.load("images/enemies/bat_spritesheet.png");
+        let texture_atlas = TextureAtlas::from_grid(texture, Vec2::new(32., 32.), 5, 1, None, None);
+        let texture_atlas = texture_atlases.add(texture_atlas);
+
+        let mut bat = Bat {
+            // Spread bats out by where they were placed
+            phase: entity_instance.px.x as f32 * 0.1,
+            ..default()
+        };
+
+        if let Some(FieldValue::Int(Some(damage))) =
+            field_value(&entity_instance.field_instances, "Damage")
+        {
+            bat.damage = *damage;
+        }
+
+        Self {
+            bat,
+            enemy: EnemyBundle::default(),
+            health: Health::new(1),
+            flying: Flying,
+            animation: AnimationStateMachine::new("fly", AnimationClip::new(0, 3, 10.))
+                .with_clip("hurt", AnimationClip::new(4, 4, 1.)),
+            texture_atlas,
+            collider: Collider::ball(8.),
+            mass: ColliderMassProperties::Density(0.1),
+        }
+    }
+}
+
+pub fn on_bat_spawn(
+    mut commands: Commands,
+    mut bats: Query<(Entity, &Bat, &mut Transform), Added<Bat>>,
+) {
+    for (entity, bat, mut transform) in bats.iter_mut() {
+        transform.translation.z = layers::ENEMIES;
+        commands.entity(entity).insert(YSorted { band: layers::ENEMIES });
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                EnemyDamageActivator(bat.damage),
+                Sensor,
+                Collider::ball(8.),
+                ActiveEvents::COLLISION_EVENTS,
+                CollisionGroups::new(Group::GROUP_5, Group::GROUP_5),
+                TransformBundle::default(),
+            ));
+        });
+    }
+}
+
+/// Flies towards the player once they're close, bobbing up and down on the way.
+pub fn ai(
+    mut bats: Query<(&mut Velocity, &mut Bat, &Transform, Option<&SpeedEffect>), Without<Stunned>>,
+    player: Query<&Transform, (With<Player>, Without<Bat>)>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    hit_stop: Res<HitStop>,
+) {
+    if *game_state != GameState::Gameplay || hit_stop.active() {
+        return;
+    }
+    let Ok(player) = player.get_single() else { return };
+
+    for (mut velocity, mut bat, transform, speed_effect) in bats.iter_mut() {
+        bat.phase += time.delta_seconds() * BOB_RATE * std::f32::consts::TAU;
+
+        let offset = (player.translation - transform.translation).truncate();
+        let chase = if offset.length() < AGGRO_RANGE {
+            offset.normalize_or_zero() * BAT_SPEED
+        } else {
+            Vec2::ZERO
+        };
+
+        // The derivative of the bob's height, so the bat follows a sine wave around its path
+        let bob = BOB_HEIGHT * BOB_RATE * std::f32::consts::TAU * bat.phase.cos();
+
+        let mut target = chase + Vec2::Y * bob;
+        if let Some(multiplier) = speed_effect {
+            target *= multiplier.multiplier;
+        }
+
+        // Steer rather than snap, so knockback still throws bats around
+        let steering = (BAT_STEERING * time.delta_seconds()).min(1.);
+        velocity.linvel += (target - velocity.linvel) * steering;
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{
+    layers,
+    player::{HudElement, MainCamera, ShowHud},
+    world::StandardFont,
+};
+
+use super::{Boss, Health};
+
+const BAR_WIDTH: f32 = 200.;
+const BAR_HEIGHT: f32 = 8.;
+/// Where the first boss's bar sits, below the timer. Any more bosses stack underneath it.
+const BAR_Y: f32 = 100.;
+const BAR_SPACING: f32 = 24.;
+
+/// The health bar at the top of the screen for the boss it belongs to.
+#[derive(Component)]
+pub struct BossBar(Entity);
+
+/// The part of a [`BossBar`] that shrinks as the boss is hurt.
+#[derive(Component)]
+pub struct BossBarFill;
+
+pub fn spawn_boss_bars(
+    mut commands: Commands,
+    bosses: Query<(Entity, &Boss), Added<Boss>>,
+    bars: Query<(), With<BossBar>>,
+    camera: Query<Entity, With<MainCamera>>,
+    font: Res<StandardFont>,
+    show_hud: Res<ShowHud>,
+) {
+    let Ok(camera) = camera.get_single() else { return };
+    let mut count = bars.iter().count();
+
+    for (boss, Boss { name, .. }) in bosses.iter() {
+        let y = BAR_Y - BAR_SPACING * count as f32;
+        count += 1;
+
+        commands.entity(camera).with_children(|parent| {
+            parent
+                .spawn((
+                    BossBar(boss),
+                    HudElement,
+                    SpatialBundle {
+                        transform: Transform::from_xyz(0., y, 0.),
+                        visibility: show_hud.visibility(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.1, 0.05, 0.05, 0.8),
+                            custom_size: Some(Vec2::new(BAR_WIDTH + 4., BAR_HEIGHT + 4.)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0., 0., layers::HUD_BACKGROUND),
+                        ..default()
+                    });
+
+                    parent.spawn((
+                        BossBarFill,
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgb(0.8, 0.1, 0.15),
+                                custom_size: Some(Vec2::new(BAR_WIDTH, BAR_HEIGHT)),
+                                // Shrinks towards the left as it scales
+                                anchor: Anchor::CenterLeft,
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(-BAR_WIDTH / 2., 0., layers::HUD),
+                            ..default()
+                        },
+                    ));
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            name.clone(),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., BAR_HEIGHT + 4., layers::HUD_TEXT),
+                        ..default()
+                    });
+                });
+        });
+    }
+}
+
+/// Keeps each bar in step with its boss's health, and removes it once the boss is gone.
+pub fn update_boss_bars(
+    mut commands: Commands,
+    bars: Query<(Entity, &BossBar, &Children)>,
+    mut fills: Query<&mut Transform, With<BossBarFill>>,
+    bosses: Query<&Health, With<Boss>>,
+) {
+    for (entity, bar, children) in bars.iter() {
+        let Ok(health) = bosses.get(bar.0) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        let fraction = (health.current.max(0) as f32 / health.max.max(1) as f32).min(1.);
+
+        for child in children.iter() {
+            if let Ok(mut transform) = fills.get_mut(*child) {
+                transform.scale.x = fraction;
+            }
+        }
+    }
+}
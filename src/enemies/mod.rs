@@ -1,32 +1,182 @@
-use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::LdtkEntityAppExt;
+use bevy::{prelude::*, utils::Duration};
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::{EntityInstance, LdtkEntityAppExt, LevelSelection}};
+use bevy_kira_audio::AudioSource;
 use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
 
+use crate::{
+    animator::{AnimationStateMachine, DamageFlash},
+    player::{
+        abilities::{insert_if_alive, BurnEffect, HealthEffect},
+        LevelBounds,
+    },
+    score::{ScoreEvent, BOSS_POINTS, ENEMY_POINTS},
+    settings::Choice,
+    world::{field_value, HitStop},
+    BonusTime, GameState, TimeBonus,
+};
+
+mod archer;
+mod bat;
+mod boss_bar;
 mod skeleton;
+pub mod waves;
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<skeleton::SkeletonBundle>("Skeleton")
+            .register_ldtk_entity::<bat::BatBundle>("Bat")
+            .register_ldtk_entity::<archer::ArcherBundle>("Archer")
+            .register_ldtk_entity::<waves::SpawnPointBundle>("SpawnPoint")
+            .insert_resource(waves::WaveSpawner::default())
             .insert_resource(DamageGiven(false))
+            .insert_resource(Difficulty::default())
+            .insert_resource(MaxEnemies::default())
+            .insert_resource(LevelScaling::default())
+            .add_system(update_level_scaling)
             .add_system(enemy_physics_checks)
             .add_system(enemy_gravity)
-            .add_system(enemy_direction);
+            .add_system(enemy_direction)
+            .add_systems((start_stuns, update_stuns))
+            .configure_set(EnemySpawnSet::Cull.before(EnemySpawnSet::Setup))
+            // The buffers are applied in between, so the setup never touches a culled enemy
+            .add_systems(
+                (
+                    cap_enemies,
+                    apply_system_buffers,
+                    skeleton::scale_skeletons,
+                    apply_system_buffers,
+                )
+                    .chain()
+                    .in_set(EnemySpawnSet::Cull),
+            )
+            .add_system(mark_bosses.in_set(EnemySpawnSet::Setup))
+            .add_systems((
+                apply_health_effects,
+                burn_effects,
+                despawn_dead.after(apply_health_effects).after(burn_effects),
+                despawn_fallen.after(despawn_dead),
+            ));
 
         app.add_systems((
-            skeleton::on_skeleton_spawn,
+            skeleton::on_skeleton_spawn.in_set(EnemySpawnSet::Setup),
             skeleton::checks,
             skeleton::ai,
-            skeleton::health_effects,
-            skeleton::health,
         ));
+
+        app.add_systems((bat::on_bat_spawn.in_set(EnemySpawnSet::Setup), bat::ai));
+
+        app.add_systems((
+            waves::run_waves,
+            waves::spawn_wave_ui,
+            waves::update_wave_ui,
+            waves::despawn_wave_ui,
+        ));
+
+        app.add_systems((
+            archer::on_archer_spawn.in_set(EnemySpawnSet::Setup),
+            archer::ai,
+            archer::arrow_hits,
+        ));
+
+        app.add_systems((boss_bar::spawn_boss_bars, boss_bar::update_boss_bars));
     }
 }
 
 #[derive(Component)]
 pub struct Enemy;
 
+/// The steps a newly spawned enemy goes through in the frame it appears.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+enum EnemySpawnSet {
+    /// Enemies over [`MaxEnemies`] or left out by the [`Difficulty`] are despawned.
+    Cull,
+    /// The enemies that are left get their sensors and components.
+    Setup,
+}
+
+/// Scales how many enemies each level has, so one layout works for every difficulty.
+/// Normal keeps the enemies placed in LDtk as they are.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// The chance an enemy placed in LDtk is left out of the level.
+    pub fn removal_chance(&self) -> f32 {
+        match self {
+            Self::Easy => 0.33,
+            Self::Normal | Self::Hard => 0.,
+        }
+    }
+
+    /// The chance an enemy placed in LDtk is joined by another one next to it.
+    pub fn extra_chance(&self) -> f32 {
+        match self {
+            Self::Hard => 0.5,
+            Self::Easy | Self::Normal => 0.,
+        }
+    }
+}
+
+impl Choice for Difficulty {
+    const ALL: &'static [Self] = &[Self::Easy, Self::Normal, Self::Hard];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Easy => "Easy",
+            Self::Normal => "Normal",
+            Self::Hard => "Hard",
+        }
+    }
+}
+
+/// Makes enemies tougher the further into a run the player gets.
+/// The first level uses the stats placed in LDtk as they are.
+#[derive(Resource, Clone, Copy, Default)]
+pub struct LevelScaling {
+    pub level: usize,
+}
+
+impl LevelScaling {
+    /// How much tougher enemies get with each level.
+    const PER_LEVEL: f32 = 0.15;
+    /// How much faster enemies get with each level. Kept lower, since fast enemies are hard to dodge.
+    const SPEED_PER_LEVEL: f32 = 0.05;
+
+    pub fn multiplier(&self) -> f32 {
+        1. + Self::PER_LEVEL * self.level as f32
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        1. + Self::SPEED_PER_LEVEL * self.level as f32
+    }
+
+    /// Scales an enemy's health, or the health change it applies to the player, which is negative for damage.
+    pub fn scale(&self, base: i32) -> i32 {
+        (base as f32 * self.multiplier()).round() as i32
+    }
+
+    /// Time added to the `GameTimer` for reaching this level, more for the harder ones.
+    pub fn time_bonus(&self) -> Duration {
+        Duration::from_secs(10 + 2 * self.level as u64)
+    }
+}
+
+fn update_level_scaling(level_selection: Res<LevelSelection>, mut scaling: ResMut<LevelScaling>) {
+    if !level_selection.is_changed() {
+        return;
+    }
+    let LevelSelection::Index(level) = *level_selection else { return };
+
+    scaling.level = level;
+}
+
 #[derive(Bundle)]
 pub struct EnemyBundle {
     pub enemy: Enemy,
@@ -52,7 +202,12 @@ impl Default for EnemyBundle {
             },
             collision_groups: CollisionGroups::new(
                 Group::GROUP_4,
-                Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_3 | Group::GROUP_4 | Group::GROUP_5,
+                Group::GROUP_1
+                    | Group::GROUP_2
+                    | Group::GROUP_3
+                    | Group::GROUP_4
+                    | Group::GROUP_5
+                    | Group::GROUP_8,
             ),
             locked_axes: LockedAxes::ROTATION_LOCKED,
             sprite: TextureAtlasSprite::default(),
@@ -60,6 +215,147 @@ impl Default for EnemyBundle {
     }
 }
 
+/// An enemy's health, shared by every kind of enemy so they all take damage and die the same way.
+#[derive(Component)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Self { current: max, max }
+    }
+
+    /// Changes the health by `amount`, which is negative for damage. Healing stops at the max.
+    pub fn change(&mut self, amount: i32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    pub fn dead(&self) -> bool {
+        self.current < 1
+    }
+}
+
+/// Stops an enemy's AI for a moment after it's hit, so knockback isn't immediately undone.
+#[derive(Component)]
+pub struct Stunned {
+    pub timer: Timer,
+}
+
+impl Default for Stunned {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(STUN_TIME, TimerMode::Once),
+        }
+    }
+}
+
+pub const STUN_TIME: f32 = 0.3;
+
+/// The animation stunned enemies go back to when the stun wears off.
+#[derive(Component)]
+struct StunnedFrom(&'static str);
+
+/// Plays the "hurt" animation for stunned enemies.
+fn start_stuns(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &mut AnimationStateMachine), (With<Enemy>, Added<Stunned>)>,
+) {
+    for (entity, mut animation) in enemies.iter_mut() {
+        insert_if_alive(&mut commands, entity, StunnedFrom(animation.state()));
+        animation.set_state("hurt");
+    }
+}
+
+fn update_stuns(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &mut Stunned, Option<&StunnedFrom>, Option<&mut AnimationStateMachine>)>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+) {
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    for (entity, mut stunned, from, animation) in enemies.iter_mut() {
+        stunned.timer.tick(time.delta());
+        if !stunned.timer.finished() {
+            continue;
+        }
+
+        if let (Some(from), Some(mut animation)) = (from, animation) {
+            animation.set_state(from.0);
+        }
+        commands.entity(entity).remove::<(Stunned, StunnedFrom)>();
+    }
+}
+
+/// An enemy that moves through the air, so it isn't pulled down by [`enemy_gravity`].
+#[derive(Component)]
+pub struct Flying;
+
+/// The most enemies that can be alive at once on each difficulty, to keep the frame rate up
+/// and the player from being swarmed. Spawns over the cap are skipped. Bosses always spawn.
+#[derive(Resource)]
+pub struct MaxEnemies {
+    pub easy: usize,
+    pub normal: usize,
+    pub hard: usize,
+}
+
+impl Default for MaxEnemies {
+    fn default() -> Self {
+        Self {
+            easy: 16,
+            normal: 24,
+            hard: 32,
+        }
+    }
+}
+
+impl MaxEnemies {
+    pub fn get(&self, difficulty: Difficulty) -> usize {
+        match difficulty {
+            Difficulty::Easy => self.easy,
+            Difficulty::Normal => self.normal,
+            Difficulty::Hard => self.hard,
+        }
+    }
+}
+
+fn is_boss(instance: Option<&EntityInstance>) -> bool {
+    matches!(
+        instance.and_then(|instance| field_value(&instance.field_instances, "Boss")),
+        Some(FieldValue::Bool(true))
+    )
+}
+
+/// Removes newly spawned enemies that would go over [`MaxEnemies`].
+/// Part of [`EnemySpawnSet::Cull`], so nothing else reacts to the skipped ones spawning.
+fn cap_enemies(
+    mut commands: Commands,
+    enemies: Query<(), With<Enemy>>,
+    spawned: Query<(Entity, Option<&EntityInstance>), Added<Enemy>>,
+    max_enemies: Res<MaxEnemies>,
+    difficulty: Res<Difficulty>,
+) {
+    let max = max_enemies.get(*difficulty);
+    let mut excess = enemies.iter().count().saturating_sub(max);
+
+    for (entity, instance) in spawned.iter() {
+        if excess == 0 {
+            break;
+        }
+        if is_boss(instance) {
+            continue;
+        }
+
+        commands.entity(entity).despawn_recursive();
+        excess -= 1;
+    }
+}
+
 #[derive(Component, Default)]
 pub struct EnemyPhysics {
     pub total_ground_collisions: i32,
@@ -122,7 +418,16 @@ fn enemy_physics_checks(
 
 const ENEMY_GRAVITY: f32 = 9.81 * 275f32;
 
-fn enemy_gravity(mut enemies: Query<(&mut Velocity, &EnemyPhysics)>, time: Res<Time>) {
+fn enemy_gravity(
+    mut enemies: Query<(&mut Velocity, &EnemyPhysics), Without<Flying>>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    hit_stop: Res<HitStop>,
+) {
+    if *game_state != GameState::Gameplay || hit_stop.active() {
+        return;
+    }
+
     for (mut velocity, physics) in enemies.iter_mut() {
         if !physics.grounded {
             velocity.linvel.y -= ENEMY_GRAVITY * time.delta_seconds();
@@ -130,7 +435,9 @@ fn enemy_gravity(mut enemies: Query<(&mut Velocity, &EnemyPhysics)>, time: Res<T
     }
 }
 
-fn enemy_direction(mut enemies: Query<(&mut TextureAtlasSprite, &Velocity), With<Enemy>>) {
+fn enemy_direction(
+    mut enemies: Query<(&mut TextureAtlasSprite, &Velocity), (With<Enemy>, Without<Stunned>)>,
+) {
     for (mut sprite, velocity) in enemies.iter_mut() {
         if velocity.linvel.x > 0.1 {
             sprite.flip_x = false;
@@ -143,5 +450,133 @@ fn enemy_direction(mut enemies: Query<(&mut TextureAtlasSprite, &Velocity), With
 #[derive(Component)]
 pub struct EnemyDamageActivator(pub i32);
 
+/// Applies [`HealthEffect`]s to enemies. Hurting any enemy counts against the no-damage run.
+fn apply_health_effects(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &mut Health, &HealthEffect)>,
+    mut damage_given: ResMut<DamageGiven>,
+) {
+    for (entity, mut health, effect) in enemies.iter_mut() {
+        health.change(effect.amount);
+        commands.entity(entity).remove::<HealthEffect>();
+        if effect.amount < 0 {
+            damage_given.0 = true;
+        }
+    }
+}
+
+fn burn_effects(
+    mut commands: Commands,
+    mut enemies: Query<(Entity, &mut Health, &mut BurnEffect)>,
+    mut damage_given: ResMut<DamageGiven>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+) {
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    for (entity, mut health, mut burn) in enemies.iter_mut() {
+        burn.timer.tick(time.delta());
+
+        if burn.timer.just_finished() {
+            health.change(burn.damage_per_tick);
+            burn.ticks_remaining = burn.ticks_remaining.saturating_sub(1);
+            damage_given.0 = true;
+            insert_if_alive(&mut commands, entity, DamageFlash::default());
+        }
+
+        if burn.ticks_remaining == 0 {
+            commands.entity(entity).remove::<BurnEffect>();
+        }
+    }
+}
+
+fn despawn_dead(
+    mut commands: Commands,
+    enemies: Query<(Entity, &Health, Option<&Boss>)>,
+    bonus: Res<BonusTime>,
+    mut time_bonus: EventWriter<TimeBonus>,
+    mut score: EventWriter<ScoreEvent>,
+) {
+    for (entity, health, boss) in enemies.iter() {
+        if health.dead() {
+            commands.entity(entity).despawn_recursive();
+            score.send(ScoreEvent(if boss.is_some() { BOSS_POINTS } else { ENEMY_POINTS }));
+
+            if !bonus.enemy.is_zero() {
+                time_bonus.send(TimeBonus(bonus.enemy));
+            }
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct DamageGiven(pub bool);
+
+/// Marks an enemy as a boss, which takes over the music while it's alive.
+/// Enemies become bosses with a "Boss" bool field in LDtk,
+/// and can be given a "BossName" and "Music" track.
+#[derive(Component)]
+pub struct Boss {
+    pub name: String,
+    pub music: Option<Handle<AudioSource>>,
+}
+
+fn mark_bosses(
+    mut commands: Commands,
+    enemies: Query<(Entity, &EntityInstance), Added<Enemy>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, instance) in enemies.iter() {
+        let fields = &instance.field_instances;
+
+        let Some(FieldValue::Bool(true)) = field_value(fields, "Boss") else { continue };
+
+        let name = match field_value(fields, "BossName") {
+            Some(FieldValue::String(Some(name))) => name.clone(),
+            _ => instance.identifier.clone(),
+        };
+
+        let music = match field_value(fields, "Music") {
+            Some(FieldValue::String(Some(path))) => Some(asset_server.load(path.as_str())),
+            _ => None,
+        };
+
+        commands.entity(entity).insert(Boss { name, music });
+    }
+}
+
+/// How far, in pixels, below the level an enemy can fall before it's cleaned up.
+const FALL_MARGIN: f32 = 64.;
+
+/// Removes enemies that fell out of the level, so they don't fall forever.
+/// They weren't beaten, so unlike [`despawn_dead`] this gives no bonus time.
+fn despawn_fallen(
+    mut commands: Commands,
+    enemies: Query<(Entity, &GlobalTransform), With<Enemy>>,
+    bounds: Res<LevelBounds>,
+) {
+    let Some(bounds) = bounds.0 else { return };
+
+    for (entity, transform) in enemies.iter() {
+        if transform.translation().y < bounds.min.y - FALL_MARGIN {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_levels_have_tougher_enemies() {
+        let first = LevelScaling { level: 0 };
+        let fourth = LevelScaling { level: 3 };
+
+        assert_eq!(first.scale(3), 3);
+        assert!(fourth.scale(3) > first.scale(3));
+        assert!(fourth.scale(-2) < first.scale(-2));
+    }
+}
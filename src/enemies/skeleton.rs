@@ -1,20 +1,31 @@
 use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::LdtkEntity;
+use bevy_ecs_ldtk::{
+    ldtk::FieldValue,
+    prelude::{EntityInstance, LdtkEntity},
+};
 use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
 
 use crate::{
     animator::*,
-    player::abilities::{HealthEffect, SpeedEffect},
+    layers,
+    player::abilities::{Potion, Resistances, SpeedEffect},
+    rng::GameRng,
+    world::{field_value, HitStop},
+    GameState,
 };
 
-use super::{EnemyBundle, EnemyDamageActivator, EnemyGroundSensor, DamageGiven};
+use super::{
+    Difficulty, Enemy, EnemyBundle, EnemyDamageActivator, EnemyGroundSensor, Health,
+    EnemyPhysics, LevelScaling, MaxEnemies, Stunned,
+};
 
 #[derive(Component)]
 pub struct Skeleton {
     pub going_right: bool,
     pub left_sensor: i32,
     pub right_sensor: i32,
-    pub hp: i32,
+    /// Health change applied to the player on contact.
+    pub damage: i32,
 }
 
 impl Default for Skeleton {
@@ -23,7 +34,7 @@ impl Default for Skeleton {
             going_right: false,
             left_sensor: 0,
             right_sensor: 0,
-            hp: 3,
+            damage: -1,
         }
     }
 }
@@ -32,8 +43,8 @@ impl Default for Skeleton {
 pub struct SkeletonBundle {
     pub skeleton: Skeleton,
     pub enemy: EnemyBundle,
-    pub animation_indices: AnimationIndices,
-    pub animation_timer: AnimationTimer,
+    pub health: Health,
+    pub animation: AnimationStateMachine,
     pub texture_atlas: Handle<TextureAtlas>,
     pub collider: Collider,
     pub mass: ColliderMassProperties,
@@ -41,22 +52,42 @@ pub struct SkeletonBundle {
 
 impl LdtkEntity for SkeletonBundle {
     fn bundle_entity(
-        _: &bevy_ecs_ldtk::EntityInstance,
+        entity_instance: &bevy_ecs_ldtk::EntityInstance,
         _: &bevy_ecs_ldtk::prelude::LayerInstance,
         _: Option<&Handle<Image>>,
         _: Option<&bevy_ecs_ldtk::prelude::TilesetDefinition>,
         asset_server: &AssetServer,
         texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        SkeletonBundle::new(entity_instance, asset_server, texture_atlases)
+    }
+}
+
+impl SkeletonBundle {
+    /// Builds a skeleton from its LDtk fields.
+    pub fn new(
+        entity_instance: &EntityInstance,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
         let texture = asset_server.load("images/enemies/skeleton_spritesheet.png");
         let texture_atlas = TextureAtlas::from_grid(texture, Vec2::new(32., 64.), 3, 2, None, None);
         let texture_atlas = texture_atlases.add(texture_atlas);
 
+        let mut skeleton = Skeleton::default();
+
+        if let Some(FieldValue::Int(Some(damage))) =
+            field_value(&entity_instance.field_instances, "Damage")
+        {
+            skeleton.damage = *damage;
+        }
+
         Self {
-            skeleton: Skeleton::default(),
+            skeleton,
             enemy: EnemyBundle::default(),
-            animation_indices: AnimationIndices { first: 0, last: 4 },
-            animation_timer: AnimationTimer(Timer::from_seconds(1. / 12., TimerMode::Repeating)),
+            health: Health::new(3),
+            animation: AnimationStateMachine::new("walk", AnimationClip::new(0, 4, 12.))
+                .with_clip("hurt", AnimationClip::new(5, 5, 1.)),
             texture_atlas,
             collider: Collider::capsule_y(20., 11.),
             mass: ColliderMassProperties::Density(0.1),
@@ -64,6 +95,28 @@ impl LdtkEntity for SkeletonBundle {
     }
 }
 
+/// Lets an enemy try to dodge potions thrown at it.
+/// Skeletons get this with an "Evasive" bool field in LDtk.
+#[derive(Component)]
+pub struct Evasive {
+    /// The probability of dodging a potion.
+    pub chance: f32,
+    /// Stops the enemy from rerolling for the same potion every frame.
+    pub cooldown: Timer,
+}
+
+impl Default for Evasive {
+    fn default() -> Self {
+        Self {
+            chance: 0.5,
+            cooldown: Timer::from_seconds(0.5, TimerMode::Once),
+        }
+    }
+}
+
+const DODGE_RADIUS: f32 = 96.;
+const DODGE_IMPULSE: f32 = 400.;
+
 #[derive(Component)]
 pub struct SkeletonSensorRight;
 
@@ -73,9 +126,102 @@ pub struct SkeletonSensorLeft;
 #[derive(Component)]
 pub struct SkeletonDamageSensor;
 
-pub fn on_skeleton_spawn(mut commands: Commands, skeletons: Query<Entity, Added<Skeleton>>) {
-    for skeleton in skeletons.iter() {
-        commands.entity(skeleton).with_children(|parent| {
+/// A skeleton added on top of the ones placed in LDtk by [`Difficulty`].
+#[derive(Component)]
+pub struct ExtraSkeleton;
+
+/// How far to the side, in pixels, an extra skeleton spawns from the one it joins.
+const EXTRA_OFFSET: f32 = 24.;
+
+/// Removes or adds skeletons as a level loads, depending on the [`Difficulty`].
+/// Bosses are always left as they were placed.
+pub fn scale_skeletons(
+    mut commands: Commands,
+    skeletons: Query<
+        (Entity, &Transform, &EntityInstance, Option<&Parent>),
+        (Added<Skeleton>, Without<ExtraSkeleton>),
+    >,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    enemies: Query<(), With<Enemy>>,
+    max_enemies: Res<MaxEnemies>,
+) {
+    let mut count = enemies.iter().count();
+
+    for (entity, transform, instance, parent) in skeletons.iter() {
+        if let Some(FieldValue::Bool(true)) = field_value(&instance.field_instances, "Boss") {
+            continue;
+        }
+
+        if rng.chance(difficulty.removal_chance()) {
+            commands.entity(entity).despawn_recursive();
+            count -= 1;
+            continue;
+        }
+
+        if count < max_enemies.get(*difficulty) && rng.chance(difficulty.extra_chance()) {
+            count += 1;
+            let side = if rng.chance(0.5) { 1. } else { -1. };
+
+            let extra = commands
+                .spawn((
+                    SkeletonBundle::new(instance, &asset_server, &mut texture_atlases),
+                    ExtraSkeleton,
+                    instance.clone(),
+                    SpatialBundle::from_transform(
+                        transform.with_translation(transform.translation + Vec3::X * side * EXTRA_OFFSET),
+                    ),
+                ))
+                .id();
+
+            // Keeps it in the level, so it's cleaned up with the rest when the level changes
+            if let Some(parent) = parent {
+                commands.entity(parent.get()).add_child(extra);
+            }
+        }
+    }
+}
+
+pub fn on_skeleton_spawn(
+    mut commands: Commands,
+    mut skeletons: Query<
+        (Entity, &Skeleton, &mut Health, &mut Transform, Option<&EntityInstance>),
+        Added<Skeleton>,
+    >,
+    scaling: Res<LevelScaling>,
+) {
+    for (entity, skeleton, mut health, mut transform, instance) in skeletons.iter_mut() {
+        *health = Health::new(scaling.scale(health.max));
+        transform.translation.z = layers::ENEMIES;
+        commands.entity(entity).insert(YSorted { band: layers::ENEMIES });
+
+        let evasive = instance
+            .and_then(|instance| field_value(&instance.field_instances, "Evasive"))
+            .map_or(false, |value| matches!(value, FieldValue::Bool(true)));
+
+        if evasive {
+            commands.entity(entity).insert(Evasive::default());
+        }
+
+        // Comma separated effect names in the "Immune" and "Resists" string fields
+        let kinds = |identifier: &str| {
+            match instance.and_then(|instance| field_value(&instance.field_instances, identifier)) {
+                Some(FieldValue::String(Some(names))) => Resistances::parse_kinds(names),
+                _ => Vec::new(),
+            }
+        };
+        let resistances = Resistances {
+            immune: kinds("Immune"),
+            resisted: kinds("Resists"),
+        };
+
+        if !resistances.immune.is_empty() || !resistances.resisted.is_empty() {
+            commands.entity(entity).insert(resistances);
+        }
+
+        commands.entity(entity).with_children(|parent| {
             parent.spawn((
                 SkeletonSensorRight,
                 Sensor,
@@ -108,7 +254,7 @@ pub fn on_skeleton_spawn(mut commands: Commands, skeletons: Query<Entity, Added<
                 ActiveHooks::MODIFY_SOLVER_CONTACTS,
                 CollisionGroups::new(
                     Group::GROUP_3,
-                    Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_4,
+                    Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_4 | Group::GROUP_8,
                 ),
                 TransformBundle {
                     local: Transform::from_xyz(0., -26., 0.),
@@ -117,7 +263,7 @@ pub fn on_skeleton_spawn(mut commands: Commands, skeletons: Query<Entity, Added<
             ));
 
             parent.spawn((
-                EnemyDamageActivator(-1),
+                EnemyDamageActivator(scaling.scale(skeleton.damage)),
                 Sensor,
                 Collider::capsule_y(12., 12.),
                 ActiveEvents::COLLISION_EVENTS,
@@ -129,17 +275,59 @@ pub fn on_skeleton_spawn(mut commands: Commands, skeletons: Query<Entity, Added<
 }
 
 pub fn ai(
-    mut skeletons: Query<(&mut Velocity, &mut Skeleton, Option<&SpeedEffect>)>,
+    mut skeletons: Query<
+        (
+            &mut Velocity,
+            &mut Skeleton,
+            Option<&SpeedEffect>,
+            &Transform,
+            &EnemyPhysics,
+            Option<&mut Evasive>,
+        ),
+        Without<Stunned>,
+    >,
+    potions: Query<(&Transform, &Velocity), (With<Potion>, Without<Skeleton>)>,
     time: Res<Time>,
+    game_state: Res<GameState>,
+    mut rng: ResMut<GameRng>,
+    hit_stop: Res<HitStop>,
+    scaling: Res<LevelScaling>,
 ) {
-    for (mut velocity, mut skeleton, speed_effect) in skeletons.iter_mut() {
+    if *game_state != GameState::Gameplay || hit_stop.active() {
+        return;
+    }
+
+    for (mut velocity, mut skeleton, speed_effect, transform, physics, evasive) in skeletons.iter_mut() {
+        if let Some(mut evasive) = evasive {
+            evasive.cooldown.tick(time.delta());
+
+            let position = transform.translation.truncate();
+
+            let incoming = potions.iter().find(|(potion_transform, potion_velocity)| {
+                let offset = position - potion_transform.translation.truncate();
+                offset.length() < DODGE_RADIUS && offset.dot(potion_velocity.linvel) > 0.
+            });
+
+            if let Some((potion_transform, _)) = incoming {
+                if evasive.cooldown.finished() {
+                    evasive.cooldown.reset();
+
+                    if physics.grounded && rng.chance(evasive.chance) {
+                        // Run away from the potion and hop out of the way
+                        skeleton.going_right = potion_transform.translation.x < transform.translation.x;
+                        velocity.linvel.y += DODGE_IMPULSE;
+                    }
+                }
+            }
+        }
+
         if skeleton.going_right && skeleton.right_sensor > 0 && skeleton.left_sensor < 1 {
             skeleton.going_right = false;
         } else if !skeleton.going_right && skeleton.right_sensor < 1 && skeleton.left_sensor > 0 {
             skeleton.going_right = true;
         }
 
-        let mut speed = 1000f32;
+        let mut speed = 1000f32 * scaling.speed_multiplier();
 
         if let Some(multiplier) = speed_effect {
             speed *= multiplier.multiplier;
@@ -206,22 +394,34 @@ pub fn checks(
     }
 }
 
-pub fn health_effects(
-    mut commands: Commands,
-    mut skeletons: Query<(Entity, &mut Skeleton, &HealthEffect)>,
-    mut damage_given: ResMut<DamageGiven>,
-) {
-    for (entity, mut skeleton, effect) in skeletons.iter_mut() {
-        skeleton.hp += effect.amount;
-        commands.entity(entity).remove::<HealthEffect>();
-        damage_given.0 = true;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The damage a freshly spawned skeleton's contact sensor deals.
+    fn contact_damage(damage: i32) -> i32 {
+        let mut world = World::new();
+        world.insert_resource(LevelScaling::default());
+        world.spawn((
+            Skeleton {
+                damage,
+                ..default()
+            },
+            Health::new(3),
+            Transform::default(),
+        ));
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(on_skeleton_spawn);
+        schedule.run(&mut world);
+
+        let mut activators = world.query::<&EnemyDamageActivator>();
+        activators.single(&world).0
     }
-}
 
-pub fn health(mut commands: Commands, skeletons: Query<(Entity, &Skeleton)>) {
-    for (entity, skeleton) in skeletons.iter() {
-        if skeleton.hp < 1 {
-            commands.entity(entity).despawn_recursive();
-        }
+    #[test]
+    fn damage_field_scales_contact_damage() {
+        assert_eq!(contact_damage(-1), -1);
+        assert_eq!(contact_damage(-2), 2 * contact_damage(-1));
     }
 }
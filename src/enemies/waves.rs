@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    layers,
+    player::{HudElement, MainCamera, ShowHud},
+    rng::GameRng,
+    world::StandardFont,
+    GameMode, GameState,
+};
+
+use super::{skeleton::SkeletonBundle, Difficulty, Enemy, MaxEnemies};
+
+/// Where enemies come from in wave mode. Placed in LDtk, and any fields are passed on to the enemies it spawns.
+#[derive(Component)]
+pub struct SpawnPoint;
+
+#[derive(Bundle)]
+pub struct SpawnPointBundle {
+    pub spawn_point: SpawnPoint,
+}
+
+impl LdtkEntity for SpawnPointBundle {
+    fn bundle_entity(
+        _: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        Self {
+            spawn_point: SpawnPoint,
+        }
+    }
+}
+
+/// Sends enemies at the player in waves, each bigger than the last, once the previous wave is dead.
+#[derive(Resource)]
+pub struct WaveSpawner {
+    /// The wave the player is on, starting at 0 before the first one spawns.
+    pub wave: u32,
+    /// The pause between clearing a wave and the next one spawning.
+    pub delay: Timer,
+}
+
+impl Default for WaveSpawner {
+    fn default() -> Self {
+        Self {
+            wave: 0,
+            delay: Timer::from_seconds(WAVE_DELAY, TimerMode::Once),
+        }
+    }
+}
+
+impl WaveSpawner {
+    /// How many enemies spawn in the current wave.
+    pub fn size(&self) -> usize {
+        FIRST_WAVE_SIZE + WAVE_GROWTH * self.wave.saturating_sub(1) as usize
+    }
+}
+
+const WAVE_DELAY: f32 = 3.;
+const FIRST_WAVE_SIZE: usize = 2;
+const WAVE_GROWTH: usize = 1;
+
+pub fn run_waves(
+    mut commands: Commands,
+    mut spawner: ResMut<WaveSpawner>,
+    spawn_points: Query<(&Transform, &EntityInstance, &Parent), With<SpawnPoint>>,
+    enemies: Query<(), With<Enemy>>,
+    mode: Res<GameMode>,
+    game_state: Res<GameState>,
+    difficulty: Res<Difficulty>,
+    max_enemies: Res<MaxEnemies>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    if *mode != GameMode::Waves || *game_state != GameState::Gameplay {
+        return;
+    }
+    if !enemies.is_empty() || spawn_points.is_empty() {
+        return;
+    }
+
+    spawner.delay.tick(time.delta());
+    if !spawner.delay.finished() {
+        return;
+    }
+    spawner.delay.reset();
+    spawner.wave += 1;
+
+    let spawn_points: Vec<_> = spawn_points.iter().collect();
+    let count = spawner.size().min(max_enemies.get(*difficulty));
+
+    for _ in 0..count {
+        let index = (rng.next_u64() % spawn_points.len() as u64) as usize;
+        let (transform, instance, parent) = spawn_points[index];
+
+        // Spread out enemies sharing a spawn point, so they don't spawn inside each other
+        let offset = Vec3::X * (rng.next_f32() * 2. - 1.) * 16.;
+
+        let transform = transform.with_translation(transform.translation + offset);
+
+        let enemy = commands
+            .spawn((
+                SkeletonBundle::new(instance, &asset_server, &mut texture_atlases),
+                SpatialBundle::from_transform(transform),
+            ))
+            .id();
+
+        // Keeps it in the level, so it's cleaned up with the rest when the level changes
+        commands.entity(parent.get()).add_child(enemy);
+    }
+}
+
+#[derive(Component)]
+pub struct WaveUi;
+
+pub fn spawn_wave_ui(
+    mut commands: Commands,
+    camera: Query<Entity, With<MainCamera>>,
+    game_state: Res<GameState>,
+    mode: Res<GameMode>,
+    ui: Query<(), With<WaveUi>>,
+    show_hud: Res<ShowHud>,
+) {
+    if *mode != GameMode::Waves {
+        return;
+    }
+
+    if game_state.is_changed() && *game_state == GameState::Gameplay && ui.is_empty() {
+        let Ok(camera) = camera.get_single() else { return };
+
+        commands.entity(camera).with_children(|parent| {
+            parent.spawn((
+                WaveUi,
+                HudElement,
+                Text2dBundle {
+                    transform: Transform::from_xyz(-200., 150., layers::HUD),
+                    visibility: show_hud.visibility(),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+pub fn update_wave_ui(
+    mut ui: Query<&mut Text, With<WaveUi>>,
+    spawner: Res<WaveSpawner>,
+    font: Res<StandardFont>,
+) {
+    let Ok(mut text) = ui.get_single_mut() else { return };
+    if !spawner.is_changed() && !text.sections.is_empty() {
+        return;
+    }
+
+    *text = Text::from_section(
+        format!("Wave {}", spawner.wave),
+        TextStyle {
+            font: font.0.clone(),
+            font_size: 20.0,
+            color: Color::WHITE,
+        },
+    )
+    .with_alignment(TextAlignment::Left);
+}
+
+pub fn despawn_wave_ui(
+    mut commands: Commands,
+    ui: Query<Entity, With<WaveUi>>,
+    game_state: Res<GameState>,
+) {
+    if game_state.is_changed() && !game_state.in_run() {
+        for ui in ui.iter() {
+            commands.entity(ui).despawn_recursive();
+        }
+    }
+}
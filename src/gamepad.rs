@@ -0,0 +1,35 @@
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+/// Input from the first connected gamepad, which reads as nothing when there isn't one.
+#[derive(SystemParam)]
+pub struct GamepadInput<'w, 's> {
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    marker: PhantomData<&'s ()>,
+}
+
+impl GamepadInput<'_, '_> {
+    fn gamepad(&self) -> Option<Gamepad> {
+        self.gamepads.iter().next()
+    }
+
+    pub fn pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepad()
+            .map_or(false, |gamepad| self.buttons.pressed(GamepadButton::new(gamepad, button_type)))
+    }
+
+    pub fn just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepad()
+            .map_or(false, |gamepad| self.buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    }
+
+    /// The position of an axis, from -1 to 1.
+    pub fn axis(&self, axis_type: GamepadAxisType) -> f32 {
+        self.gamepad()
+            .and_then(|gamepad| self.axes.get(GamepadAxis::new(gamepad, axis_type)))
+            .unwrap_or_default()
+    }
+}
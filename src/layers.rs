@@ -0,0 +1,33 @@
+//! The z values everything is drawn at, so draw order is predictable.
+//!
+//! LDtk places the layers of a level one unit apart, starting from 0 for the bottom layer.
+//! In `map.ldtk` that puts the Entities layer at 3 and the Foreground at 5,
+//! so entities in the world are spread between 3 and 4:
+//! in front of the background, but behind the foreground.
+//!
+//! HUD elements are children of the camera, so their z is relative to it and must stay negative.
+
+/// Pickups and other static entities. LDtk already places entities here.
+pub const WORLD: f32 = 3.0;
+pub const ENEMIES: f32 = 3.2;
+pub const PLAYER: f32 = 3.4;
+pub const POTIONS: f32 = 3.6;
+/// The range y-sorted entities are drawn in when y-sorting is enabled,
+/// spanning the enemy and player bands so they can be drawn in front of each other.
+pub const Y_SORTED_MIN: f32 = ENEMIES;
+pub const Y_SORTED_MAX: f32 = POTIONS;
+/// Splashes and particles.
+pub const EFFECTS: f32 = 3.8;
+/// Drawn over the foreground, like the aim preview.
+pub const OVERLAY: f32 = 6.0;
+
+/// Panels behind HUD icons.
+pub const HUD_BACKGROUND: f32 = -2.0;
+/// Hearts, ability icons and text.
+pub const HUD: f32 = -1.0;
+/// Text drawn on top of HUD icons.
+pub const HUD_TEXT: f32 = -0.9;
+/// Menus shown over the game, like the pause menu.
+pub const MENU: f32 = -0.75;
+/// Fullscreen filters over everything, like the brightness overlay.
+pub const SCREEN_FILTER: f32 = -0.5;
@@ -0,0 +1,241 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::prelude::{LdtkAsset, LevelSelection};
+
+use crate::{
+    campaign::SavedRun,
+    player::MaxHealth,
+    storage,
+    unlocks::PracticeRun,
+    world::{LevelCount, MapPath, StandardFont},
+    GameMode, GameState,
+};
+
+const SAVE_FILE: &str = "progress";
+
+pub struct LevelSelectPlugin;
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Progress::load())
+            .insert_resource(LevelSelectState::default())
+            .add_system(record_progress)
+            .add_system(spawn_level_select)
+            .add_system(level_select)
+            .add_system(update_level_select.after(level_select))
+            .add_system(despawn_level_select);
+    }
+}
+
+/// How far through the campaign the player has got, kept between sessions.
+#[derive(Resource, Default)]
+pub struct Progress {
+    /// The furthest level index reached. Every level up to it can be picked from the level select.
+    pub unlocked: usize,
+}
+
+impl Progress {
+    fn save(&self) {
+        let mut values = HashMap::new();
+        values.insert("unlocked".to_owned(), self.unlocked.to_string());
+
+        storage::save(SAVE_FILE, &values);
+    }
+
+    fn load() -> Self {
+        let values = storage::load(SAVE_FILE);
+
+        Self {
+            unlocked: values.get("unlocked").and_then(|value| value.parse().ok()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Unlocks each level as the campaign reaches it, which means the one before was completed.
+fn record_progress(
+    level_selection: Res<LevelSelection>,
+    game_state: Res<GameState>,
+    mode: Res<GameMode>,
+    mut progress: ResMut<Progress>,
+) {
+    if !level_selection.is_changed() || !game_state.in_run() || *mode != GameMode::Campaign {
+        return;
+    }
+    let LevelSelection::Index(level) = *level_selection else { return };
+
+    if level > progress.unlocked {
+        progress.unlocked = level;
+        progress.save();
+    }
+}
+
+#[derive(Resource, Default)]
+struct LevelSelectState {
+    selected: usize,
+    /// Kept loaded while the menu is open, so the [`LevelCount`] is known.
+    /// The world only loads the map once gameplay starts.
+    map: Option<Handle<LdtkAsset>>,
+}
+
+#[derive(Component)]
+struct LevelSelectMenu;
+
+/// The row of level numbers, rebuilt as the selection moves.
+#[derive(Component)]
+struct LevelList;
+
+fn spawn_level_select(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    font: Res<StandardFont>,
+    asset_server: Res<AssetServer>,
+    map: Res<MapPath>,
+    mut state: ResMut<LevelSelectState>,
+    progress: Res<Progress>,
+) {
+    if !game_state.is_changed() || *game_state != GameState::LevelSelect {
+        return;
+    }
+
+    // Start on the furthest level, since that's usually where the player wants to be
+    state.selected = progress.unlocked;
+    state.map = Some(asset_server.load(map.0.as_str()));
+
+    commands
+        .spawn(LevelSelectMenu)
+        .insert(SpatialBundle::default())
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "Level Select",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 50.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., 96.0, 0.),
+                ..default()
+            });
+
+            parent.spawn((
+                LevelList,
+                Text2dBundle {
+                    text: Text::default().with_alignment(TextAlignment::Center),
+                    ..default()
+                },
+            ));
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[A/D to Select, Space to Play, Esc to Return]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., -128.0, 0.),
+                ..default()
+            });
+        });
+}
+
+fn level_select(
+    mut commands: Commands,
+    mut game_state: ResMut<GameState>,
+    keys: Res<Input<KeyCode>>,
+    mut state: ResMut<LevelSelectState>,
+    progress: Res<Progress>,
+    level_count: Res<LevelCount>,
+    mut mode: ResMut<GameMode>,
+    mut saved: ResMut<SavedRun>,
+    max_health: Res<MaxHealth>,
+) {
+    if *game_state != GameState::LevelSelect {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        *game_state = GameState::StartMenu;
+        return;
+    }
+
+    // Nothing can be picked until it's known which levels there are
+    if level_count.0 == 0 {
+        return;
+    }
+    // A smaller mod map can have fewer levels than were unlocked
+    let last = progress.unlocked.min(level_count.0 - 1);
+    state.selected = state.selected.min(last);
+
+    if keys.just_pressed(KeyCode::A) {
+        state.selected = state.selected.saturating_sub(1);
+    }
+    if keys.just_pressed(KeyCode::D) {
+        state.selected = (state.selected + 1).min(last);
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        *game_state = GameState::Gameplay;
+        *mode = GameMode::Campaign;
+        crate::reset_run(&mut commands, &max_health);
+        saved.clear();
+        commands.insert_resource(LevelSelection::Index(state.selected));
+        // Skipping ahead isn't beating the game, so it can't earn unlocks or records
+        commands.insert_resource(PracticeRun(state.selected > 0));
+    }
+}
+
+fn update_level_select(
+    game_state: Res<GameState>,
+    state: Res<LevelSelectState>,
+    progress: Res<Progress>,
+    level_count: Res<LevelCount>,
+    font: Res<StandardFont>,
+    mut list: Query<&mut Text, With<LevelList>>,
+) {
+    if *game_state != GameState::LevelSelect {
+        return;
+    }
+    let Ok(mut text) = list.get_single_mut() else { return };
+
+    let style = |color| TextStyle {
+        font: font.0.clone(),
+        font_size: 24.0,
+        color,
+    };
+
+    if level_count.0 == 0 {
+        text.sections = vec![TextSection::new("Loading levels...", style(Color::WHITE))];
+        return;
+    }
+
+    text.sections = (0..level_count.0)
+        .map(|level| {
+            let color = if level == state.selected {
+                Color::GOLD
+            } else if level <= progress.unlocked {
+                Color::WHITE
+            } else {
+                Color::DARK_GRAY
+            };
+            TextSection::new(format!(" {} ", level + 1), style(color))
+        })
+        .collect();
+}
+
+fn despawn_level_select(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    menu: Query<Entity, With<LevelSelectMenu>>,
+    mut state: ResMut<LevelSelectState>,
+) {
+    if game_state.is_changed() && *game_state != GameState::LevelSelect {
+        for menu in menu.iter() {
+            commands.entity(menu).despawn_recursive();
+        }
+        state.map = None;
+    }
+}
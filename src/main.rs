@@ -1,71 +1,166 @@
-use bevy::{app::AppExit, prelude::*};
+use bevy::{
+    app::AppExit,
+    asset::LoadState,
+    prelude::*,
+    render::{
+        settings::{PowerPreference, WgpuLimits, WgpuSettings},
+        RenderPlugin,
+    },
+};
 
 #[cfg(feature = "native")]
 use bevy::{window::PrimaryWindow, winit::WinitWindows};
+use animator::{ParticleQuality, SimpleMotion};
 use bevy::utils::Duration;
 use bevy_ecs_ldtk::LevelSelection;
-use enemies::DamageGiven;
-use player::{MainCamera, PlayerHealth};
-use world::{StandardFont, CursiveFont};
+use campaign::{BestTime, SavedRun};
+use enemies::{waves::WaveSpawner, DamageGiven};
+use player::{DamageTaken, MainCamera, MaxHealth, PlayerHealth, SlamCombo};
+use rng::GameRng;
+use score::{HighScores, Score};
+use unlocks::{NewUnlocks, PracticeRun};
+use world::{Checkpoint, CursiveFont, RestartLevel, StandardFont, WinReason};
 
 mod animator;
+mod campaign;
 #[cfg(debug_assertions)]
 mod debug;
 mod enemies;
+mod gamepad;
+mod layers;
+mod level_select;
+#[cfg(feature = "native")]
+mod modding;
 mod player;
+mod rng;
+mod score;
+mod settings;
 mod sound;
+mod storage;
+mod unlocks;
 mod world;
 
 const GAME_TIME: u64 = 180;
 
+/// Whether to boot a minimal configuration, for machines where the game crashes on startup.
+/// Enabled with `--safe` on native, or `?safe` in the page URL on the web.
+fn safe_mode() -> bool {
+    #[cfg(feature = "native")]
+    return std::env::args().any(|arg| arg == "--safe");
+
+    #[cfg(feature = "browser")]
+    return stdweb::web::document()
+        .location()
+        .and_then(|location| location.search().ok())
+        .map_or(false, |search| search.contains("safe"));
+}
+
 fn main() {
     let mut app = App::new();
 
-    app.add_plugins(
-        DefaultPlugins
-            .set(ImagePlugin::default_nearest())
-            .set(WindowPlugin {
-                primary_window: Some(Window {
-                    title: "Potion Panic!".into(),
-                    canvas: Some("#game".to_owned()),
-                    fit_canvas_to_parent: true,
-                    resize_constraints: WindowResizeConstraints {
-                        min_width: 480.,
-                        min_height: 320.,
-                        max_width: 2400.,
-                        max_height: 1600.,
-                    },
-                    ..default()
-                }),
+    let safe_mode = safe_mode();
+
+    let mut plugins = DefaultPlugins
+        .set(ImagePlugin::default_nearest())
+        .set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Potion Panic!".into(),
+                canvas: Some("#game".to_owned()),
+                fit_canvas_to_parent: true,
+                resize_constraints: WindowResizeConstraints {
+                    min_width: 480.,
+                    min_height: 320.,
+                    max_width: 2400.,
+                    max_height: 1600.,
+                },
                 ..default()
             }),
-    )
-    .add_plugin(world::WorldPlugin)
-    .add_plugin(animator::AnimatorPlugin)
-    .add_plugin(sound::SoundPlugin)
-    .add_plugin(player::PlayerPlugin)
-    .add_plugin(enemies::EnemyPlugin);
+            ..default()
+        });
+
+    if safe_mode {
+        plugins = plugins.set(RenderPlugin {
+            wgpu_settings: WgpuSettings {
+                power_preference: PowerPreference::LowPower,
+                limits: WgpuLimits::downlevel_webgl2_defaults(),
+                ..default()
+            },
+        });
+    }
+
+    // The asset server has to exist before the asset plugin, or it makes its own
+    #[cfg(feature = "native")]
+    let mod_dir = modding::mod_dir();
+    #[cfg(feature = "native")]
+    if let Some(dir) = &mod_dir {
+        app.insert_resource(modding::asset_server(dir));
+    }
+
+    app.add_plugins(plugins)
+        .add_plugin(world::WorldPlugin)
+        .add_plugin(animator::AnimatorPlugin);
+
+    // Audio backends are a common cause of crashes on startup
+    if !safe_mode {
+        app.add_plugin(sound::SoundPlugin);
+    }
+
+    app.add_plugin(player::PlayerPlugin)
+        .add_plugin(enemies::EnemyPlugin)
+        .add_plugin(settings::SettingsPlugin)
+        .add_plugin(campaign::CampaignPlugin)
+        .add_plugin(unlocks::UnlocksPlugin)
+        .add_plugin(score::ScorePlugin)
+        .add_plugin(level_select::LevelSelectPlugin);
+
+    #[cfg(feature = "native")]
+    if let Some(dir) = mod_dir {
+        app.add_plugin(modding::ModdingPlugin { dir });
+    }
+
+    if safe_mode {
+        warn!("Safe mode is active: audio and multisampling are disabled, and rendering uses downlevel limits");
+        app.insert_resource(Msaa::Off);
+    }
 
     #[cfg(debug_assertions)]
     app.add_plugin(debug::DebugPlugin);
 
     app.insert_resource(GameState::StartMenu);
-    app.insert_resource(GameTimer(Timer::new(
-        Duration::from_secs(GAME_TIME),
-        TimerMode::Once,
-    )));
-    app.add_startup_system(spawn_start_menu);
+    app.insert_resource(GameMode::Campaign);
+    app.insert_resource(GameTimer::default());
+    app.insert_resource(BonusTime::default());
+    app.insert_resource(rng::GameRng::default());
+    app.add_event::<TimeBonus>();
+    app.add_system(spawn_start_menu);
     app.add_system(start_menu);
     app.add_system(despawn_start_menu);
+    app.add_system(drift_menu_potions);
+
+    app.add_system(spawn_pause_menu);
+    app.add_system(pause);
+    app.add_system(despawn_pause_menu);
 
-    app.add_system(spawn_game_over);
+    app.add_system(spawn_game_over.after(score::finish_score));
     app.add_system(game_over);
     app.add_system(despawn_game_over);
 
-    app.add_system(spawn_win_screen);
+    app.add_system(
+        spawn_win_screen
+            .after(unlocks::unlock_on_win)
+            .after(campaign::record_best_time)
+            .after(score::finish_score),
+    );
     app.add_system(win_screen);
     app.add_system(despawn_win_screen);
 
+    app.insert_resource(ErrorMessage::default());
+    app.add_startup_system(load_critical_assets);
+    app.add_system(check_critical_assets);
+    app.add_system(spawn_error_screen);
+    app.add_system(error_screen);
+    app.add_system(despawn_error_screen);
+
     #[cfg(feature = "native")]
     app.add_startup_system(set_window_icon);
 
@@ -78,16 +173,165 @@ pub enum GameState {
     Gameplay,
     GameOver,
     WinScreen,
+    Settings,
+    Paused,
+    Controls,
+    /// Picking a level to start the campaign from, see [`level_select::Progress`].
+    LevelSelect,
+    /// Something the game can't run without failed to load. The reason is in [`ErrorMessage`].
+    Error,
+}
+
+/// How a run is played, chosen from the start menu.
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// Playing through the levels in order.
+    Campaign,
+    /// Fighting off endless waves of enemies, see [`enemies::waves::WaveSpawner`].
+    /// There's no clock, and the level's exit is a pit to fall into rather than a way on.
+    Waves,
+}
+
+impl GameState {
+    /// Whether a run is in progress, even if it's paused.
+    pub fn in_run(&self) -> bool {
+        matches!(self, Self::Gameplay | Self::Paused)
+    }
 }
 
 #[derive(Resource)]
-pub struct GameTimer(pub Timer);
+pub struct GameTimer(Timer);
+
+impl Default for GameTimer {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(GAME_TIME))
+    }
+}
+
+impl GameTimer {
+    pub fn new(duration: Duration) -> Self {
+        Self(Timer::new(duration, TimerMode::Once))
+    }
+
+    /// A timer for a run that's already been going for `elapsed`, with `remaining` left.
+    pub fn resume(elapsed: Duration, remaining: Duration) -> Self {
+        let mut timer = Timer::new(elapsed + remaining, TimerMode::Once);
+        timer.set_elapsed(elapsed);
+        Self(timer)
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.0.tick(delta);
+    }
+
+    pub fn finished(&self) -> bool {
+        self.0.finished()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.remaining()
+    }
+
+    pub fn remaining_secs(&self) -> f32 {
+        self.0.remaining_secs()
+    }
+
+    /// Extends the remaining time, keeping the time already elapsed.
+    pub fn add_time(&mut self, time: Duration) {
+        self.set_duration(self.0.duration() + time);
+    }
+
+    /// Shortens the remaining time, keeping the time already elapsed.
+    /// Removing more than what remains finishes the timer on the next tick.
+    pub fn remove_time(&mut self, time: Duration) {
+        let duration = self.0.duration().saturating_sub(time).max(self.0.elapsed());
+        self.set_duration(duration);
+    }
+
+    /// Changes the total duration, keeping the time already elapsed.
+    ///
+    /// A finished `Once` timer stays finished even if its duration grows,
+    /// so the timer is rebuilt instead of resized.
+    pub fn set_duration(&mut self, duration: Duration) {
+        let elapsed = self.0.elapsed().min(duration);
+        let mut timer = Timer::new(duration, TimerMode::Once);
+        timer.set_elapsed(elapsed);
+        self.0 = timer;
+    }
+}
+
+/// How much time is added to the `GameTimer` for engaging with the level.
+/// Both default to zero, which leaves the base mode unchanged.
+#[derive(Resource, Default)]
+pub struct BonusTime {
+    pub enemy: Duration,
+    pub pickup: Duration,
+}
+
+/// Sent to add time to the `GameTimer`.
+pub struct TimeBonus(pub Duration);
+
+/// Puts back everything a run changes, for starting a new one.
+fn reset_run(commands: &mut Commands, max_health: &MaxHealth) {
+    commands.insert_resource(GameTimer::default());
+    commands.insert_resource(PlayerHealth(max_health.0));
+    commands.insert_resource(LevelSelection::Index(0));
+    commands.insert_resource(DamageGiven(false));
+    commands.insert_resource(DamageTaken(false));
+    commands.insert_resource(SlamCombo::default());
+    commands.insert_resource(WaveSpawner::default());
+    commands.insert_resource(Checkpoint::default());
+    commands.insert_resource(PracticeRun(false));
+    commands.insert_resource(Score::default());
+}
+
+/// Puts the player back at the start of the level they're on, for practicing it.
+/// Only health and the score are reset, unless the clock ran out, since there'd be no time to practice with.
+fn restart_level(
+    commands: &mut Commands,
+    restart: &mut EventWriter<RestartLevel>,
+    max_health: &MaxHealth,
+    timer: &GameTimer,
+) {
+    commands.insert_resource(PlayerHealth(max_health.0));
+    commands.insert_resource(PracticeRun(true));
+    // Practice doesn't go on the high-score table, so there's nothing to carry on counting
+    commands.insert_resource(Score::default());
+    if timer.remaining_secs() <= 0. {
+        commands.insert_resource(GameTimer::default());
+    }
+    // The world is only still around when restarting from the pause menu,
+    // otherwise entering gameplay loads the level fresh anyway
+    restart.send(RestartLevel);
+}
 
 #[derive(Component)]
 struct StartMenu;
 
-fn spawn_start_menu(mut commands: Commands, game_state: Res<GameState>, font: Res<StandardFont>) {
-    if *game_state != GameState::StartMenu {
+/// A potion drifting slowly behind the start menu.
+#[derive(Component)]
+struct MenuPotion {
+    spin: f32,
+}
+
+const MENU_POTIONS: usize = 8;
+/// How far past the edge of the screen potions drift before wrapping around to the other side.
+const MENU_BOUNDS: Vec2 = Vec2::new(256., 176.);
+
+fn spawn_start_menu(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    font: Res<StandardFont>,
+    saved: Res<SavedRun>,
+    asset_server: Res<AssetServer>,
+    mut rng: ResMut<GameRng>,
+    quality: Res<ParticleQuality>,
+) {
+    if !game_state.is_changed() || *game_state != GameState::StartMenu {
         return;
     }
 
@@ -95,6 +339,30 @@ fn spawn_start_menu(mut commands: Commands, game_state: Res<GameState>, font: Re
         .spawn(StartMenu)
         .insert(SpatialBundle::default())
         .with_children(|parent| {
+            let colors = ["green", "purple"];
+            for i in 0..quality.scale(MENU_POTIONS) {
+                let position = Vec2::new(rng.next_f32() * 2. - 1., rng.next_f32() * 2. - 1.) * MENU_BOUNDS;
+                let velocity = Vec2::new(rng.next_f32() * 2. - 1., rng.next_f32() * 2. - 1.) * 12.;
+                let texture = format!("images/abilities/{}_small.png", colors[i % colors.len()]);
+
+                parent.spawn((
+                    MenuPotion {
+                        spin: (rng.next_f32() * 2. - 1.) * 0.5,
+                    },
+                    SimpleMotion { velocity, gravity: 0. },
+                    SpriteBundle {
+                        texture: asset_server.load(texture),
+                        sprite: Sprite {
+                            // Faint, so it stays behind the text
+                            color: Color::rgba(1., 1., 1., 0.25),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(position.extend(-1.)),
+                        ..default()
+                    },
+                ));
+            }
+
             parent.spawn(Text2dBundle {
                 text: Text::from_section(
                     "Potion Panic!",
@@ -121,16 +389,138 @@ fn spawn_start_menu(mut commands: Commands, game_state: Res<GameState>, font: Re
                 transform: Transform::from_xyz(0., -64.0, 0.),
                 ..default()
             });
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[Press Tab for Settings]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., -96.0, 0.),
+                ..default()
+            });
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[Press C for Controls]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., -128.0, 0.),
+                ..default()
+            });
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[Press L for Level Select]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., -160.0, 0.),
+                ..default()
+            });
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[Press W for Wave Mode]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., 96.0, 0.),
+                ..default()
+            });
+
+            if saved.0.is_some() {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        "[Press Enter to Continue]",
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 20.0,
+                            color: Color::GOLD,
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    transform: Transform::from_xyz(0., 64.0, 0.),
+                    ..default()
+                });
+            }
         });
 }
 
-fn start_menu(mut game_state: ResMut<GameState>, keys: Res<Input<KeyCode>>) {
+fn start_menu(
+    mut commands: Commands,
+    mut game_state: ResMut<GameState>,
+    keys: Res<Input<KeyCode>>,
+    mut saved: ResMut<SavedRun>,
+    max_health: Res<MaxHealth>,
+    mut mode: ResMut<GameMode>,
+) {
     if *game_state != GameState::StartMenu {
         return;
     }
 
     if keys.just_pressed(KeyCode::Space) {
         *game_state = GameState::Gameplay;
+        *mode = GameMode::Campaign;
+        reset_run(&mut commands, &max_health);
+        saved.clear();
+    }
+
+    if keys.just_pressed(KeyCode::W) {
+        *game_state = GameState::Gameplay;
+        *mode = GameMode::Waves;
+        reset_run(&mut commands, &max_health);
+    }
+
+    if keys.just_pressed(KeyCode::Return) {
+        if let Some(run) = saved.0 {
+            *game_state = GameState::Gameplay;
+            *mode = GameMode::Campaign;
+            run.restore(&mut commands, &max_health);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        *game_state = GameState::Settings;
+    }
+
+    if keys.just_pressed(KeyCode::C) {
+        *game_state = GameState::Controls;
+    }
+
+    if keys.just_pressed(KeyCode::L) {
+        *game_state = GameState::LevelSelect;
+    }
+}
+
+/// Spins the menu potions and wraps them around the screen, so they drift forever.
+fn drift_menu_potions(mut potions: Query<(&MenuPotion, &mut Transform)>, time: Res<Time>) {
+    for (potion, mut transform) in potions.iter_mut() {
+        transform.rotate_z(potion.spin * time.delta_seconds());
+
+        for axis in 0..2 {
+            let bound = MENU_BOUNDS[axis];
+            if transform.translation[axis].abs() > bound {
+                transform.translation[axis] -= 2. * bound * transform.translation[axis].signum();
+            }
+        }
     }
 }
 
@@ -145,14 +535,135 @@ fn despawn_start_menu(
     }
 }
 
+#[derive(Component)]
+struct PauseMenu;
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    font: Res<StandardFont>,
+    camera: Query<Entity, With<MainCamera>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if game_state.is_changed() && *game_state == GameState::Paused {
+        let Ok(camera) = camera.get_single() else { return };
+
+        commands.entity(camera).with_children(|parent| {
+            parent
+                .spawn(PauseMenu)
+                .insert(SpatialBundle::from_transform(Transform::from_xyz(0., 0., layers::MENU)))
+                .with_children(|parent| {
+                    parent.spawn(ColorMesh2dBundle {
+                        mesh: meshes
+                            .add(shape::Quad::new(Vec2::new(480., 320.)).into())
+                            .into(),
+                        material: materials.add(ColorMaterial::from(Color::rgba(0., 0., 0., 0.5))),
+                        ..default()
+                    });
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "Paused",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 75.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., 0., 0.1),
+                        ..default()
+                    });
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "[Press Esc to Resume]",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -64.0, 0.1),
+                        ..default()
+                    });
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "[Press R to Restart Level]",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -88.0, 0.1),
+                        ..default()
+                    });
+                });
+        });
+    }
+}
+
+fn pause(
+    mut commands: Commands,
+    mut game_state: ResMut<GameState>,
+    keys: Res<Input<KeyCode>>,
+    mut restart: EventWriter<RestartLevel>,
+    max_health: Res<MaxHealth>,
+    timer: Res<GameTimer>,
+) {
+    if *game_state == GameState::Paused && keys.just_pressed(KeyCode::R) {
+        *game_state = GameState::Gameplay;
+        restart_level(&mut commands, &mut restart, &max_health, &timer);
+        return;
+    }
+
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    if *game_state == GameState::Gameplay {
+        *game_state = GameState::Paused;
+    } else if *game_state == GameState::Paused {
+        *game_state = GameState::Gameplay;
+    }
+}
+
+fn despawn_pause_menu(
+    mut commands: Commands,
+    pause_menu: Query<Entity, With<PauseMenu>>,
+    game_state: Res<GameState>,
+) {
+    if game_state.is_changed() && *game_state != GameState::Paused {
+        for pause_menu in pause_menu.iter() {
+            commands.entity(pause_menu).despawn_recursive();
+        }
+    }
+}
+
 #[derive(Component)]
 struct GameOver;
 
+/// Whether dying can be undone by going back to the last checkpoint.
+/// Running out of time can't, since the clock carries on from where it was.
+fn can_respawn(checkpoint: &Checkpoint, timer: &GameTimer) -> bool {
+    checkpoint.last.is_some() && timer.remaining_secs() > 0.
+}
+
 fn spawn_game_over(
     mut commands: Commands,
     game_state: Res<GameState>,
     font: Res<StandardFont>,
     camera: Query<Entity, With<MainCamera>>,
+    checkpoint: Res<Checkpoint>,
+    timer: Res<GameTimer>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
 ) {
     if game_state.is_changed() && *game_state == GameState::GameOver {
         let Ok(camera) = camera.get_single() else { return };
@@ -175,6 +686,15 @@ fn spawn_game_over(
                         ..default()
                     });
 
+                    score::spawn_score_summary(
+                        parent,
+                        &font,
+                        &score,
+                        (*mode == GameMode::Campaign).then_some(&*high_scores),
+                        Color::RED,
+                        84.,
+                    );
+
                     parent.spawn(Text2dBundle {
                         text: Text::from_section(
                             "[Press Space to Restart]",
@@ -189,6 +709,20 @@ fn spawn_game_over(
                         ..default()
                     });
 
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "[Press R to Restart Level]",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 20.0,
+                                color: Color::RED,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -88.0, 0.),
+                        ..default()
+                    });
+
                     #[cfg(feature = "native")]
                     parent.spawn(Text2dBundle {
                         text: Text::from_section(
@@ -200,9 +734,25 @@ fn spawn_game_over(
                             },
                         )
                         .with_alignment(TextAlignment::Center),
-                        transform: Transform::from_xyz(0., -96.0, 0.),
+                        transform: Transform::from_xyz(0., -112.0, 0.),
                         ..default()
                     });
+
+                    if can_respawn(&checkpoint, &timer) {
+                        parent.spawn(Text2dBundle {
+                            text: Text::from_section(
+                                "[Press C to Respawn at Checkpoint]",
+                                TextStyle {
+                                    font: font.0.clone(),
+                                    font_size: 20.0,
+                                    color: Color::RED,
+                                },
+                            )
+                            .with_alignment(TextAlignment::Center),
+                            transform: Transform::from_xyz(0., -136.0, 0.),
+                            ..default()
+                        });
+                    }
                 });
         });
     }
@@ -213,6 +763,10 @@ fn game_over(
     mut game_state: ResMut<GameState>,
     keys: Res<Input<KeyCode>>,
     mut exit: EventWriter<AppExit>,
+    max_health: Res<MaxHealth>,
+    mut checkpoint: ResMut<Checkpoint>,
+    timer: Res<GameTimer>,
+    mut restart: EventWriter<RestartLevel>,
 ) {
     if *game_state != GameState::GameOver {
         return;
@@ -220,13 +774,22 @@ fn game_over(
 
     if keys.just_pressed(KeyCode::Space) {
         *game_state = GameState::Gameplay;
-        commands.insert_resource(GameTimer(Timer::new(
-            Duration::from_secs(GAME_TIME),
-            TimerMode::Once,
-        )));
-        commands.insert_resource(PlayerHealth::default());
-        commands.insert_resource(LevelSelection::Index(0));
-        commands.insert_resource(DamageGiven(false));
+        reset_run(&mut commands, &max_health);
+    }
+
+    if keys.just_pressed(KeyCode::R) {
+        *game_state = GameState::Gameplay;
+        restart_level(&mut commands, &mut restart, &max_health, &timer);
+    }
+
+    // Only health comes back, and the clock keeps going from where it was
+    if keys.just_pressed(KeyCode::C) && can_respawn(&checkpoint, &timer) {
+        if let Some((level, _)) = checkpoint.last {
+            *game_state = GameState::Gameplay;
+            checkpoint.respawning = true;
+            commands.insert_resource(PlayerHealth(max_health.0));
+            commands.insert_resource(LevelSelection::Index(level));
+        }
     }
 
     if keys.just_pressed(KeyCode::Q) {
@@ -256,7 +819,15 @@ fn spawn_win_screen(
     cursive_font: Res<CursiveFont>,
     camera: Query<Entity, With<MainCamera>>,
     damage_given: Res<DamageGiven>,
-    player_health: Res<PlayerHealth>,
+    damage_taken: Res<DamageTaken>,
+    combo: Res<SlamCombo>,
+    new_unlocks: Res<NewUnlocks>,
+    best_time: Res<BestTime>,
+    timer: Res<GameTimer>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
+    win_reason: Res<WinReason>,
 ) {
     if game_state.is_changed() && *game_state == GameState::WinScreen {
         let Ok(camera) = camera.get_single() else { return };
@@ -279,6 +850,25 @@ fn spawn_win_screen(
                         ..default()
                     });
 
+                    let reason_text = match *win_reason {
+                        WinReason::GoldHeart => "The gold heart is yours!",
+                        WinReason::OutOfLevels => "You made it through every level!",
+                    };
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            reason_text,
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 16.0,
+                                color: Color::GOLD,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -40.0, 0.),
+                        ..default()
+                    });
+
                     parent.spawn(Text2dBundle {
                         text: Text::from_section(
                             "[Press Space to Play Again]",
@@ -293,6 +883,20 @@ fn spawn_win_screen(
                         ..default()
                     });
 
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "[Press R to Replay Level]",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 20.0,
+                                color: Color::GOLD,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -84.0, 0.),
+                        ..default()
+                    });
+
                     #[cfg(feature = "native")]
                     parent.spawn(Text2dBundle {
                         text: Text::from_section(
@@ -304,11 +908,11 @@ fn spawn_win_screen(
                             },
                         )
                         .with_alignment(TextAlignment::Center),
-                        transform: Transform::from_xyz(0., -96.0, 0.),
+                        transform: Transform::from_xyz(0., -104.0, 0.),
                         ..default()
                     });
 
-                    let damage_taken_color = if player_health.0 == 6 {
+                    let damage_taken_color = if !damage_taken.0 {
                         Color::GREEN
                     } else {
                         Color::RED
@@ -347,6 +951,54 @@ fn spawn_win_screen(
                         transform: Transform::from_xyz(128., -128.0, 0.),
                         ..default()
                     });
+
+                    let time = campaign::format_time(timer.elapsed());
+                    let time_text = if best_time.new_record {
+                        format!("Time: {time} (New Best!)")
+                    } else if let Some(best) = best_time.best {
+                        format!("Time: {time}   Best: {}", campaign::format_time(best))
+                    } else {
+                        format!("Time: {time}")
+                    };
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            format!("{time_text}   Best slam combo: {}", combo.best),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 16.0,
+                                color: Color::GOLD,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., 58.0, 0.),
+                        ..default()
+                    });
+
+                    score::spawn_score_summary(
+                        parent,
+                        &font,
+                        &score,
+                        (*mode == GameMode::Campaign).then_some(&*high_scores),
+                        Color::GOLD,
+                        100.,
+                    );
+
+                    for (i, cloak) in new_unlocks.0.iter().enumerate() {
+                        parent.spawn(Text2dBundle {
+                            text: Text::from_section(
+                                format!("Unlocked {} Cloak: {}", cloak.name(), cloak.requirement()),
+                                TextStyle {
+                                    font: font.0.clone(),
+                                    font_size: 14.0,
+                                    color: cloak.color(),
+                                },
+                            )
+                            .with_alignment(TextAlignment::Center),
+                            transform: Transform::from_xyz(0., 150.0 - 14.0 * i as f32, 0.),
+                            ..default()
+                        });
+                    }
                 });
         });
     }
@@ -357,6 +1009,9 @@ fn win_screen(
     mut game_state: ResMut<GameState>,
     keys: Res<Input<KeyCode>>,
     mut exit: EventWriter<AppExit>,
+    max_health: Res<MaxHealth>,
+    timer: Res<GameTimer>,
+    mut restart: EventWriter<RestartLevel>,
 ) {
     if *game_state != GameState::WinScreen {
         return;
@@ -364,13 +1019,12 @@ fn win_screen(
 
     if keys.just_pressed(KeyCode::Space) {
         *game_state = GameState::Gameplay;
-        commands.insert_resource(GameTimer(Timer::new(
-            Duration::from_secs(GAME_TIME),
-            TimerMode::Once,
-        )));
-        commands.insert_resource(PlayerHealth::default());
-        commands.insert_resource(LevelSelection::Index(0));
-        commands.insert_resource(DamageGiven(false));
+        reset_run(&mut commands, &max_health);
+    }
+
+    if keys.just_pressed(KeyCode::R) {
+        *game_state = GameState::Gameplay;
+        restart_level(&mut commands, &mut restart, &max_health, &timer);
     }
 
     if keys.just_pressed(KeyCode::Q) {
@@ -397,16 +1051,24 @@ fn set_window_icon(
 ) {
     let Ok(primary) = primary.get_single() else { return; };
 
-    let (icon_rgba, icon_width, icon_height) = {
-        let image = image::open("assets/images/logo.png")
-            .expect("Failed to open icon path")
-            .into_rgba8();
-        let (width, height) = image.dimensions();
-        let rgba = image.into_raw();
-        (rgba, width, height)
+    // The icon is nice to have, so the game carries on without it
+    let image = match image::open("assets/images/logo.png") {
+        Ok(image) => image.into_rgba8(),
+        Err(err) => {
+            warn!("Failed to open icon: {err}");
+            return;
+        }
     };
 
-    let icon = winit::window::Icon::from_rgba(icon_rgba, icon_width, icon_height).unwrap();
+    let (icon_width, icon_height) = image.dimensions();
+
+    let icon = match winit::window::Icon::from_rgba(image.into_raw(), icon_width, icon_height) {
+        Ok(icon) => icon,
+        Err(err) => {
+            warn!("Failed to create icon: {err}");
+            return;
+        }
+    };
 
     let Some(window_id) = winit_windows.entity_to_winit.get(&primary) else { return };
 
@@ -414,3 +1076,235 @@ fn set_window_icon(
 
     window.set_window_icon(Some(icon));
 }
+
+/// What went wrong, shown on the error screen.
+#[derive(Resource, Default)]
+pub struct ErrorMessage(pub String);
+
+impl ErrorMessage {
+    /// Logs `message` and switches to the error screen, instead of crashing.
+    pub fn report(&mut self, game_state: &mut GameState, message: impl Into<String>) {
+        self.0 = message.into();
+        error!("{}", self.0);
+        *game_state = GameState::Error;
+    }
+}
+
+/// Assets the game can't run without.
+#[derive(Resource)]
+struct CriticalAssets(Vec<HandleUntyped>);
+
+fn load_critical_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font: Res<StandardFont>,
+    cursive_font: Res<CursiveFont>,
+) {
+    let mut handles = vec![font.0.clone_untyped(), cursive_font.0.clone_untyped()];
+
+    for path in [
+        "images/cloak_spritesheet.png",
+        "images/enemies/skeleton_spritesheet.png",
+        "images/cooldown.png",
+    ] {
+        handles.push(asset_server.load_untyped(path));
+    }
+
+    commands.insert_resource(CriticalAssets(handles));
+}
+
+fn check_critical_assets(
+    critical_assets: Res<CriticalAssets>,
+    asset_server: Res<AssetServer>,
+    mut game_state: ResMut<GameState>,
+    mut error: ResMut<ErrorMessage>,
+) {
+    if *game_state == GameState::Error {
+        return;
+    }
+
+    for handle in critical_assets.0.iter() {
+        if asset_server.get_load_state(handle) == LoadState::Failed {
+            let path = asset_server
+                .get_handle_path(handle)
+                .map(|path| path.path().display().to_string())
+                .unwrap_or_default();
+
+            error.report(&mut game_state, format!("Couldn't load {path}"));
+            return;
+        }
+    }
+}
+
+#[derive(Component)]
+struct ErrorScreen;
+
+fn spawn_error_screen(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    font: Res<StandardFont>,
+    error: Res<ErrorMessage>,
+    camera: Query<Entity, With<MainCamera>>,
+) {
+    if game_state.is_changed() && *game_state == GameState::Error {
+        let Ok(camera) = camera.get_single() else { return };
+
+        commands.entity(camera).with_children(|parent| {
+            parent
+                .spawn(ErrorScreen)
+                .insert(SpatialBundle::default())
+                .with_children(|parent| {
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "Something Went Wrong",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 40.0,
+                                color: Color::RED,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., 32.0, 0.),
+                        ..default()
+                    });
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            error.0.clone(),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 16.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -16.0, 0.),
+                        ..default()
+                    });
+
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "[Press Space to Retry]",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 20.0,
+                                color: Color::RED,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -64.0, 0.),
+                        ..default()
+                    });
+
+                    #[cfg(feature = "native")]
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            "[Press Q to Quit]",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 20.0,
+                                color: Color::RED,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -96.0, 0.),
+                        ..default()
+                    });
+                });
+        });
+    }
+}
+
+fn error_screen(
+    mut game_state: ResMut<GameState>,
+    keys: Res<Input<KeyCode>>,
+    mut exit: EventWriter<AppExit>,
+    critical_assets: Res<CriticalAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    if *game_state != GameState::Error {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        // Try loading anything that failed again. The map is reloaded when a new run starts.
+        for handle in critical_assets.0.iter() {
+            if asset_server.get_load_state(handle) == LoadState::Failed {
+                if let Some(path) = asset_server.get_handle_path(handle) {
+                    asset_server.reload_asset(path);
+                }
+            }
+        }
+
+        *game_state = GameState::StartMenu;
+    }
+
+    if keys.just_pressed(KeyCode::Q) {
+        exit.send(AppExit);
+    }
+}
+
+fn despawn_error_screen(
+    mut commands: Commands,
+    error_screen: Query<Entity, With<ErrorScreen>>,
+    game_state: Res<GameState>,
+) {
+    if game_state.is_changed() && *game_state != GameState::Error {
+        for error_screen in error_screen.iter() {
+            commands.entity(error_screen).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer_at(elapsed: u64, duration: u64) -> GameTimer {
+        let mut timer = GameTimer::new(Duration::from_secs(duration));
+        timer.tick(Duration::from_secs(elapsed));
+        timer
+    }
+
+    #[test]
+    fn adding_time_keeps_elapsed() {
+        let mut timer = timer_at(30, 60);
+        timer.add_time(Duration::from_secs(20));
+
+        assert_eq!(timer.elapsed(), Duration::from_secs(30));
+        assert_eq!(timer.remaining(), Duration::from_secs(50));
+    }
+
+    #[test]
+    fn adding_time_revives_a_finished_timer() {
+        let mut timer = timer_at(60, 60);
+        assert!(timer.finished());
+
+        timer.add_time(Duration::from_secs(10));
+        timer.tick(Duration::ZERO);
+
+        assert!(!timer.finished());
+        assert_eq!(timer.remaining(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn removing_more_than_remains_finishes() {
+        let mut timer = timer_at(30, 60);
+        timer.remove_time(Duration::from_secs(45));
+        timer.tick(Duration::ZERO);
+
+        assert!(timer.finished());
+        assert_eq!(timer.remaining(), Duration::ZERO);
+        assert_eq!(timer.elapsed(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn shrinking_below_elapsed_finishes() {
+        let mut timer = timer_at(30, 60);
+        timer.set_duration(Duration::from_secs(10));
+        timer.tick(Duration::ZERO);
+
+        assert!(timer.finished());
+        assert_eq!(timer.remaining(), Duration::ZERO);
+    }
+}
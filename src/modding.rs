@@ -0,0 +1,164 @@
+//! Loading custom content from a mod directory on native, without recompiling.
+//!
+//! The directory is given with `--mod <dir>` or the `POTION_PANIC_MOD` environment variable.
+//! It mirrors the bundled `assets` folder, and any file in it is used instead of the bundled one:
+//!
+//! ```text
+//! my_mod/
+//!     config.txt                  Tuning, see below
+//!     map.ldtk                    Replaces the levels
+//!     images/cloak_spritesheet.png
+//!     images/enemies/...
+//!     audio/PotionPanic.wav
+//! ```
+//!
+//! Files that aren't there, or a directory that doesn't exist, fall back to the bundled assets.
+//!
+//! `config.txt` has one `key=value` per line, and every key is optional:
+//! - `map`: the map to load, relative to the assets, like `custom.ldtk`
+//! - `potion_drag`: see [`PotionDrag`]
+//! - `max_potions`: see [`MaxPotions`]
+//! - `max_enemies_easy`, `max_enemies_normal`, `max_enemies_hard`: see [`MaxEnemies`]
+
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{AssetIo, AssetIoError, FileAssetIo, Metadata},
+    prelude::*,
+    utils::{BoxedFuture, HashMap},
+};
+
+use crate::{
+    enemies::MaxEnemies,
+    player::abilities::{MaxPotions, PotionDrag},
+    storage,
+    world::MapPath,
+};
+
+const CONFIG_FILE: &str = "config.txt";
+
+/// The mod directory to load from, if one was given.
+/// Relative paths are made absolute here, since Bevy would otherwise resolve them from the executable.
+pub fn mod_dir() -> Option<PathBuf> {
+    let mut args = std::env::args();
+    let from_args = args.position(|arg| arg == "--mod").and_then(|_| args.next());
+
+    let dir = PathBuf::from(from_args.or_else(|| std::env::var("POTION_PANIC_MOD").ok())?);
+    Some(std::env::current_dir().map_or(dir.clone(), |cwd| cwd.join(dir)))
+}
+
+/// An asset server that looks in the mod directory first, then the bundled assets.
+/// It has to be inserted before the `AssetPlugin` is added, so it's used instead of the default.
+pub fn asset_server(dir: &Path) -> AssetServer {
+    AssetServer::new(ModAssetIo {
+        overrides: FileAssetIo::new(dir, false),
+        bundled: FileAssetIo::new(AssetPlugin::default().asset_folder, false),
+    })
+}
+
+struct ModAssetIo {
+    overrides: FileAssetIo,
+    bundled: FileAssetIo,
+}
+
+impl AssetIo for ModAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            match self.overrides.load_path(path).await {
+                Err(AssetIoError::NotFound(_)) => self.bundled.load_path(path).await,
+                result => result,
+            }
+        })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        self.overrides
+            .read_directory(path)
+            .or_else(|_| self.bundled.read_directory(path))
+    }
+
+    fn get_metadata(&self, path: &Path) -> Result<Metadata, AssetIoError> {
+        self.overrides
+            .get_metadata(path)
+            .or_else(|_| self.bundled.get_metadata(path))
+    }
+
+    fn watch_path_for_changes(
+        &self,
+        to_watch: &Path,
+        to_reload: Option<PathBuf>,
+    ) -> Result<(), AssetIoError> {
+        self.bundled.watch_path_for_changes(to_watch, to_reload)
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        self.bundled.watch_for_changes()
+    }
+}
+
+/// Applies the tuning in the mod's `config.txt` over the defaults.
+pub struct ModdingPlugin {
+    pub dir: PathBuf,
+}
+
+impl Plugin for ModdingPlugin {
+    fn build(&self, app: &mut App) {
+        // Logging only starts with the default plugins, so this is the first place to warn from
+        if self.dir.is_dir() {
+            info!("Loading mod from {}", self.dir.display());
+        } else {
+            warn!("The mod directory {} doesn't exist, using the bundled assets", self.dir.display());
+        }
+
+        let config = std::fs::read_to_string(self.dir.join(CONFIG_FILE))
+            .map(|text| storage::parse(&text))
+            .unwrap_or_default();
+
+        app.insert_resource(ModConfig(config))
+            .add_startup_system(apply_mod_config);
+    }
+}
+
+#[derive(Resource)]
+struct ModConfig(HashMap<String, String>);
+
+impl ModConfig {
+    fn get<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        let value = self.0.get(key)?;
+        let parsed = value.parse().ok();
+        if parsed.is_none() {
+            warn!("Ignoring {key}={value} in the mod config, since it isn't valid");
+        }
+        parsed
+    }
+}
+
+fn apply_mod_config(
+    config: Res<ModConfig>,
+    mut map: ResMut<MapPath>,
+    mut drag: ResMut<PotionDrag>,
+    mut max_potions: ResMut<MaxPotions>,
+    mut max_enemies: ResMut<MaxEnemies>,
+) {
+    if let Some(path) = config.0.get("map") {
+        map.0 = path.clone();
+    }
+    if let Some(value) = config.get::<f32>("potion_drag") {
+        drag.0 = value.max(0.);
+    }
+    if let Some(value) = config.get("max_potions") {
+        max_potions.0 = value;
+    }
+    if let Some(value) = config.get("max_enemies_easy") {
+        max_enemies.easy = value;
+    }
+    if let Some(value) = config.get("max_enemies_normal") {
+        max_enemies.normal = value;
+    }
+    if let Some(value) = config.get("max_enemies_hard") {
+        max_enemies.hard = value;
+    }
+}
@@ -1,7 +1,3 @@
-use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
-
-use crate::animator::*;
-
 use super::*;
 
 #[derive(Component)]
@@ -25,69 +21,65 @@ impl Ability for GreenPotion {
         -120.
     }
 
+    fn name() -> &'static str {
+        "Green"
+    }
+
+    fn max_ammo() -> u32 {
+        10
+    }
+
+    fn cooldown() -> f32 {
+        0.75
+    }
+
+    fn splash_color() -> Color {
+        Color::rgb(0.4, 0.9, 0.3)
+    }
+
+    fn launch_velocity() -> Vec2 {
+        Vec2::new(400., 200.)
+    }
+
+    fn effects() -> Vec<PotionEffect> {
+        vec![
+            PotionEffect::Damage(1),
+            PotionEffect::Slow(2.0),
+            PotionEffect::Knockback(POTION_KNOCKBACK),
+        ]
+    }
+
+    fn player_effects() -> Vec<PotionEffect> {
+        vec![PotionEffect::Buff(BuffKind::Speed(1.5), 3.0)]
+    }
+
     fn activate(
         mut commands: Commands,
+        pool: &mut PotionPool,
+        _player: Entity,
         position: Vec3,
         velocity: Velocity,
-        right: bool,
+        direction: Vec2,
+        power: f32,
         asset_server: &AssetServer,
     ) {
-        let new_velocity =
-            Vec2::new(if right { 400. } else { -400. }, 200.) + velocity.linvel * 0.5;
-
-        commands.spawn((
-            PotionBundle::default(),
-            GreenPotion,
-            SpriteBundle {
-                texture: asset_server.load("images/abilities/green_small.png"),
-                transform: Transform::from_translation(position),
-                ..default()
-            },
-            Velocity {
-                linvel: new_velocity,
-                angvel: 10.0,
-            },
-        ));
-    }
-}
-
-pub fn checks(
-    mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
-    potions: Query<(Entity, &Transform), With<GreenPotion>>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-) {
-    for collision_event in collision_events.iter() {
-        let CollisionEvent::Started(a, b, flags) = collision_event else { continue };
-
-        if *flags & CollisionEventFlags::SENSOR == CollisionEventFlags::SENSOR {
-            continue;
-        }
-
-        let (entity, transform, other) = if let Ok((entity, transform)) = potions.get(*a) {
-            (entity, transform, *b)
-        } else if let Ok((entity, transform)) = potions.get(*b) {
-            (entity, transform, *a)
-        } else {
-            continue;
-        };
+        let new_velocity = launch_velocity(Self::launch_velocity(), direction, velocity, power);
 
-        commands
-            .entity(other)
-            .insert(HealthEffect { amount: -1 })
-            .insert(SpeedEffect { multiplier: 2.0 })
-            .insert(DamageFlash::default());
-        commands.entity(entity).despawn();
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: GreenPotion::splash_image(&asset_server, &mut texture_atlases),
-                transform: *transform,
-                ..default()
-            },
-            AnimationIndices { first: 0, last: 6 },
-            AnimationTimer(Timer::from_seconds(1. / 12., TimerMode::Repeating)),
-            Destruct,
-        ));
+        pool.spawn(
+            &mut commands,
+            (
+                PotionBundle::default(),
+                GreenPotion,
+                SpriteBundle {
+                    texture: asset_server.load("images/abilities/green_small.png"),
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+                Velocity {
+                    linvel: new_velocity,
+                    angvel: 10.0,
+                },
+            ),
+        );
     }
 }
@@ -1,19 +1,114 @@
-use bevy::{input::mouse::MouseWheel, prelude::*};
-use bevy_rapier2d::prelude::*;
+use std::any::TypeId;
 
-use crate::GameState;
+use bevy::{prelude::*, utils::HashMap, window::PrimaryWindow};
+use bevy_ecs_ldtk::prelude::LdtkEntityAppExt;
+use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
 
-use super::{MainCamera, Player};
+use crate::{
+    animator::{
+        AnimationClip, AnimationStateMachine, DamageFlash, Droplet, Lifetime, OnAnimationEnd,
+        ParticleQuality, SimpleMotion, DROPLET_GRAVITY,
+    },
+    enemies::{Enemy, Stunned},
+    layers,
+    rng::GameRng,
+    world::{HitStop, StandardFont},
+    GameState,
+};
+
+use super::{
+    controls::ActionInput,
+    BuffKind, FloatingText, HudElement, HudLayout, MainCamera, Player, PlayerBuff, ShowHud,
+};
 
 mod green;
 mod purple;
+mod red;
+mod refill;
+mod yellow;
 
 use green::GreenPotion;
 use purple::PurplePotion;
+use red::RedPotion;
+use yellow::YellowPotion;
 
 #[derive(Component)]
 pub struct Potion;
 
+/// A modifier that lets the player's own potions hit them.
+#[derive(Resource)]
+pub struct ChaosPotions(pub bool);
+
+/// A potion far enough from the player that it won't hit them as it's thrown.
+#[derive(Component)]
+struct Armed;
+
+fn arm_chaos_potions(
+    mut commands: Commands,
+    chaos: Res<ChaosPotions>,
+    mut potions: Query<(Entity, &Transform, &mut CollisionGroups), (With<Potion>, Without<Armed>)>,
+    player: Query<&Transform, With<Player>>,
+) {
+    if !chaos.0 {
+        return;
+    }
+    let Ok(player) = player.get_single() else { return };
+
+    for (entity, transform, mut groups) in potions.iter_mut() {
+        if transform.translation.distance(player.translation) > 24. {
+            groups.filters |= Group::GROUP_2;
+            commands.entity(entity).insert(Armed);
+        }
+    }
+}
+
+/// How many potion entities are spawned up front for [`PotionPool`].
+const POOL_SIZE: usize = 8;
+
+/// A potion entity parked in [`PotionPool`], waiting to be thrown again.
+#[derive(Component)]
+pub struct Pooled;
+
+/// Potion entities that have shattered, kept around so throwing doesn't spawn and despawn every time.
+#[derive(Resource, Default)]
+pub struct PotionPool {
+    pub free: Vec<Entity>,
+}
+
+impl PotionPool {
+    /// Reuses a parked potion entity for `bundle`, or spawns a new one if the pool has run dry.
+    pub fn spawn(&mut self, commands: &mut Commands, bundle: impl Bundle) -> Entity {
+        match self.free.pop() {
+            Some(entity) => {
+                commands
+                    .entity(entity)
+                    .remove::<(Pooled, RigidBodyDisabled, ColliderDisabled)>()
+                    .insert(bundle);
+                entity
+            }
+            None => commands.spawn(bundle).id(),
+        }
+    }
+
+    /// Parks a potion entity of type `A` so it can be reused by [`PotionPool::spawn`].
+    pub fn release<A: Component>(&mut self, commands: &mut Commands, entity: Entity) {
+        // A potion can hit two things in the same frame
+        if self.free.contains(&entity) {
+            return;
+        }
+
+        commands
+            .entity(entity)
+            .remove::<(Potion, A, Armed, Lifetime)>()
+            .insert((Pooled, RigidBodyDisabled, ColliderDisabled, Visibility::Hidden));
+        self.free.push(entity);
+    }
+}
+
+/// The most potions that can be in flight at once. Throws are blocked at the cap.
+#[derive(Resource)]
+pub struct MaxPotions(pub usize);
+
 #[derive(Bundle)]
 pub struct PotionBundle {
     pub potion: Potion,
@@ -22,6 +117,7 @@ pub struct PotionBundle {
     pub active_events: ActiveEvents,
     pub collision_groups: CollisionGroups,
     pub dominance: Dominance,
+    pub lifetime: Lifetime,
 }
 
 impl Default for PotionBundle {
@@ -36,6 +132,8 @@ impl Default for PotionBundle {
                 filters: Group::GROUP_4 | Group::GROUP_1,
             },
             dominance: Dominance { groups: -1 },
+            // Cleans up potions thrown out of the level
+            lifetime: Lifetime::from_seconds(10.),
         }
     }
 }
@@ -50,34 +148,401 @@ pub trait Ability {
 
     fn ui_position() -> f32;
 
+    /// The name used to refer to the ability in LDtk.
+    fn name() -> &'static str;
+
+    /// How many times the ability can be used before it needs refilling.
+    fn max_ammo() -> u32;
+
+    /// How long, in seconds, before the ability can be used again.
+    fn cooldown() -> f32;
+
+    /// Whether the ability throws a potion, rather than affecting the player directly.
+    fn thrown() -> bool {
+        true
+    }
+
+    /// The color of the droplets the potion splashes when it shatters.
+    fn splash_color() -> Color {
+        Color::WHITE
+    }
+
+    /// The velocity the potion is thrown with when facing right, before aiming and charging.
+    /// Its length is how fast the potion is thrown, and its angle is the arc of an unaimed throw.
+    fn launch_velocity() -> Vec2 {
+        DEFAULT_LAUNCH
+    }
+
+    /// What the potion does to an enemy it hits.
+    fn effects() -> Vec<PotionEffect> {
+        Vec::new()
+    }
+
+    /// What the potion does to the player, when [`ChaosPotions`] lets it hit them.
+    fn player_effects() -> Vec<PotionEffect> {
+        Vec::new()
+    }
+
+    /// Throws the potion from `position` along `direction`, which is normalized.
+    /// `power` scales how hard it's thrown, depending on how long the throw was charged.
     fn activate(
         commands: Commands,
+        pool: &mut PotionPool,
+        player: Entity,
         position: Vec3,
         velocity: Velocity,
-        right: bool,
+        direction: Vec2,
+        power: f32,
         asset_server: &AssetServer,
     );
 }
 
+const SPLASH_DROPLETS: usize = 8;
+
+/// Spawns a potion's splash animation where it shattered, along with a burst of droplets.
+/// Returns the splash animation's entity.
+pub fn spawn_splash(
+    commands: &mut Commands,
+    texture_atlas: Handle<TextureAtlas>,
+    transform: Transform,
+    color: Color,
+    quality: ParticleQuality,
+    rng: &mut GameRng,
+) -> Entity {
+    let mut transform = transform;
+    transform.translation.z = layers::EFFECTS;
+
+    // The shatter finishes on the middle frame, which is when a hit lands
+    let splash = commands
+        .spawn((
+            SpriteSheetBundle {
+                texture_atlas,
+                transform,
+                ..default()
+            },
+            AnimationStateMachine::new("shatter", AnimationClip::new(0, 3, 12.).then("fade"))
+                .with_clip("fade", AnimationClip::new(4, 6, 12.).once()),
+            // Gone once all 7 frames have played
+            Lifetime::from_seconds(7. / 12.),
+        ))
+        .id();
+
+    for _ in 0..quality.scale(SPLASH_DROPLETS) {
+        let angle = rng.next_f32() * std::f32::consts::PI;
+        let speed = 60. + rng.next_f32() * 60.;
+
+        commands.spawn((
+            Droplet,
+            SimpleMotion {
+                velocity: Vec2::from_angle(angle) * speed,
+                gravity: DROPLET_GRAVITY,
+            },
+            Lifetime::from_seconds(0.4 + rng.next_f32() * 0.2),
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(transform.translation),
+                ..default()
+            },
+        ));
+    }
+
+    splash
+}
+
+/// The launch velocity potions are thrown with unless their ability changes it.
+pub const DEFAULT_LAUNCH: Vec2 = Vec2::new(400., 200.);
+
+/// The direction potions are thrown when there's no cursor to aim with,
+/// following the arc of the ability's `launch`.
+pub fn facing_direction(launch: Vec2, right: bool) -> Vec2 {
+    Vec2::new(if right { launch.x } else { -launch.x }, launch.y).normalize_or_zero()
+}
+
+/// The velocity a potion is thrown with, inheriting some of the player's velocity.
+/// It's thrown as fast as the ability's `launch`, in whichever direction it was aimed.
+pub fn launch_velocity(launch: Vec2, direction: Vec2, velocity: Velocity, power: f32) -> Vec2 {
+    direction * launch.length() * power + velocity.linvel * 0.5
+}
+
+/// How long the throw button has been held, from 0 to 1.
+#[derive(Resource, Default)]
+pub struct ThrowCharge {
+    pub charge: f32,
+    /// The ability being charged, or `None` when the button isn't held.
+    pub ability: Option<usize>,
+}
+
+impl ThrowCharge {
+    /// Seconds of holding for a full charge.
+    const TIME: f32 = 1.0;
+
+    /// The throw power for the current charge, from a tap's 0.5 to a full charge's 1.5.
+    pub fn power(&self) -> f32 {
+        0.5 + self.charge
+    }
+}
+
+/// Aims throws with the keyboard instead of the mouse, for players without one.
+/// The angle is raised and lowered with the aim keys, and mirrored to the way the player faces.
+#[derive(Resource)]
+pub struct KeyboardAim {
+    pub enabled: bool,
+    /// Radians above the horizontal.
+    pub angle: f32,
+}
+
+impl Default for KeyboardAim {
+    fn default() -> Self {
+        let direction = facing_direction(DEFAULT_LAUNCH, true);
+        Self {
+            enabled: false,
+            angle: direction.y.atan2(direction.x),
+        }
+    }
+}
+
+impl KeyboardAim {
+    const MIN_ANGLE: f32 = -std::f32::consts::FRAC_PI_3;
+    const MAX_ANGLE: f32 = 1.4;
+    /// Radians per second the angle turns while an aim key is held.
+    const SPEED: f32 = 1.5;
+
+    /// The direction to throw in, or nothing when aiming with the mouse.
+    pub fn direction(&self, right: bool) -> Option<Vec2> {
+        let x = if right { self.angle.cos() } else { -self.angle.cos() };
+        self.enabled.then(|| Vec2::new(x, self.angle.sin()))
+    }
+}
+
+fn adjust_keyboard_aim(
+    mut aim: ResMut<KeyboardAim>,
+    input: ActionInput,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+) {
+    if !aim.enabled || *game_state != GameState::Gameplay {
+        return;
+    }
+
+    let turn = input.aim_axis();
+    if turn != 0. {
+        aim.angle = (aim.angle + turn * KeyboardAim::SPEED * time.delta_seconds())
+            .clamp(KeyboardAim::MIN_ANGLE, KeyboardAim::MAX_ANGLE);
+    }
+}
+
+/// Where a potion is thrown from and the direction it's thrown in,
+/// aiming with the keyboard when that's enabled, or at the cursor when there is one.
+pub fn aim(
+    transform: &Transform,
+    sprite: &TextureAtlasSprite,
+    cursor: Option<Vec2>,
+    keyboard: &KeyboardAim,
+    launch: Vec2,
+) -> (Vec3, Vec2) {
+    let direction = keyboard
+        .direction(!sprite.flip_x)
+        .or_else(|| {
+            cursor
+                .map(|cursor| (cursor - transform.translation.truncate()).normalize_or_zero())
+                .filter(|direction| *direction != Vec2::ZERO)
+        })
+        .unwrap_or_else(|| facing_direction(launch, !sprite.flip_x));
+
+    let position = if direction.x >= 0. {
+        transform.translation + Vec3::X * 12.
+    } else {
+        transform.translation - Vec3::X * 12.
+    };
+
+    (position.truncate().extend(layers::POTIONS), direction)
+}
+
+/// Converts the cursor's position in the window into a position in the world.
+pub fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+
+    // The cursor is relative to the window, but the camera expects it relative to its letterboxed viewport
+    let offset = camera
+        .viewport
+        .as_ref()
+        .map(|viewport| viewport.physical_position.as_vec2() / window.scale_factor() as f32)
+        .unwrap_or_default();
+
+    camera
+        .viewport_to_world(camera_transform, cursor - offset)
+        .map(|ray| ray.origin.truncate())
+}
+
+/// An ability's trait functions, so that abilities of different types can be stored together.
+#[derive(Clone, Copy)]
+pub struct RegisteredAbility {
+    id: TypeId,
+    pub name: &'static str,
+    pub max_ammo: u32,
+    pub ui_image: fn(&AssetServer) -> Handle<Image>,
+    pub ui_position: f32,
+    pub cooldown: f32,
+    pub thrown: bool,
+    pub launch_velocity: Vec2,
+    pub activate: fn(Commands, &mut PotionPool, Entity, Vec3, Velocity, Vec2, f32, &AssetServer),
+}
+
+/// Every ability the player can cycle through, in order.
+#[derive(Resource, Default)]
+pub struct Abilities(Vec<RegisteredAbility>);
+
+impl Abilities {
+    pub fn with<A: Ability + 'static>(mut self) -> Self {
+        self.0.push(RegisteredAbility {
+            id: TypeId::of::<A>(),
+            name: A::name(),
+            max_ammo: A::max_ammo(),
+            ui_image: A::ui_image,
+            ui_position: A::ui_position(),
+            cooldown: A::cooldown(),
+            thrown: A::thrown(),
+            launch_velocity: A::launch_velocity(),
+            activate: A::activate,
+        });
+        self
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RegisteredAbility> {
+        self.0.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &RegisteredAbility)> {
+        self.0.iter().enumerate()
+    }
+
+    /// The index of ability `A`, which must have been registered.
+    pub fn index_of<A: Ability + 'static>(&self) -> usize {
+        self.0
+            .iter()
+            .position(|ability| ability.id == TypeId::of::<A>())
+            .expect("ability isn't registered")
+    }
+
+    pub fn index_by_name(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|ability| ability.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// How many more times each ability can be used, by index in [`Abilities`].
+#[derive(Resource, Default)]
+pub struct AbilityAmmo(Vec<u32>);
+
+impl AbilityAmmo {
+    pub fn full(abilities: &Abilities) -> Self {
+        Self(abilities.iter().map(|(_, ability)| ability.max_ammo).collect())
+    }
+
+    pub fn get(&self, ability: usize) -> u32 {
+        self.0.get(ability).copied().unwrap_or_default()
+    }
+
+    pub fn refill(&mut self, ability: usize, abilities: &Abilities) {
+        let (Some(ammo), Some(ability)) = (self.0.get_mut(ability), abilities.get(ability)) else { return };
+        *ammo = ability.max_ammo;
+    }
+
+    fn take(&mut self, ability: usize) -> bool {
+        match self.0.get_mut(ability) {
+            Some(ammo) if *ammo > 0 => {
+                *ammo -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 pub struct AbilityPlugin;
 
 impl Plugin for AbilityPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ActiveAbility::Green)
+        let abilities = Abilities::default()
+            .with::<GreenPotion>()
+            .with::<PurplePotion>()
+            .with::<RedPotion>()
+            .with::<YellowPotion>();
+
+        app.insert_resource(abilities)
+            .insert_resource(ActiveAbility::default())
+            .insert_resource(StartingAbility::default())
+            .insert_resource(AbilityAmmo::default())
+            .insert_resource(ThrowCharge::default())
+            .insert_resource(KeyboardAim::default())
+            .add_system(adjust_keyboard_aim.before(use_ability))
             .insert_resource(AbilityCooldown::default())
             .add_system(spawn_ability_ui)
+            .add_system(reset_ammo)
+            .add_system(reset_active_ability.before(spawn_ability_ui))
             .add_system(update_active_ability)
             .add_system(despawn_ability_ui)
             .add_system(update_ability_ui)
             .add_system(use_ability)
             .add_system(update_cooldowns)
-            .add_system(update_potion_gravity);
+            .add_system(clear_cooldowns)
+            .add_event::<AbilityReady>()
+            .add_event::<PotionLanded>()
+            .insert_resource(CooldownRefund(0.0))
+            .insert_resource(MaxPotions(8))
+            .insert_resource(PotionDrag::default())
+            .insert_resource(ChaosPotions(false))
+            .add_system(arm_chaos_potions)
+            .add_system(refund_missed_cooldowns.before(update_cooldowns))
+            .add_system(flash_ready_abilities.after(update_cooldowns))
+            .add_system(update_ready_flash)
+            .add_system(update_potion_gravity)
+            .add_system(update_trajectory_preview);
+
+        let free = (0..POOL_SIZE)
+            .map(|_| {
+                app.world
+                    .spawn((
+                        Pooled,
+                        RigidBodyDisabled,
+                        ColliderDisabled,
+                        SpatialBundle {
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                    ))
+                    .id()
+            })
+            .collect();
+        app.insert_resource(PotionPool { free });
 
         // Green
-        app.add_system(green::checks);
+        app.add_system(potion_hits::<GreenPotion>);
 
         // Purple
-        app.add_system(purple::checks);
+        app.add_system(potion_hits::<PurplePotion>);
+
+        // Red
+        app.add_system(potion_hits::<RedPotion>);
+
+        // Refills
+        app.register_ldtk_entity::<refill::PotionRefillBundle>("PotionRefill")
+            .add_system(refill::checks);
 
         let asset_server = app.world.resource::<AssetServer>();
         let texture = asset_server.load("images/cooldown.png");
@@ -91,80 +556,82 @@ impl Plugin for AbilityPlugin {
     }
 }
 
-#[derive(Resource, PartialEq, Eq)]
-pub enum ActiveAbility {
-    Green,
-    Purple,
-}
+/// The index of the selected ability in [`Abilities`].
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ActiveAbility(pub usize);
 
 impl ActiveAbility {
-    pub fn add(&mut self) {
-        *self = match self {
-            Self::Green => Self::Purple,
-            Self::Purple => Self::Green,
-        };
-    }
-
-    pub fn subtract(&mut self) {
-        *self = match self {
-            Self::Green => Self::Purple,
-            Self::Purple => Self::Green,
-        };
+    pub fn add(&mut self, count: usize) {
+        if count > 0 {
+            self.0 = (self.0 + 1) % count;
+        }
     }
 
-    pub fn ui_position(&self) -> f32 {
-        match self {
-            Self::Green => GreenPotion::ui_position(),
-            Self::Purple => PurplePotion::ui_position(),
+    pub fn subtract(&mut self, count: usize) {
+        if count > 0 {
+            self.0 = (self.0 + count - 1) % count;
         }
     }
 
     pub fn activate(
         &self,
         mut commands: Commands,
+        pool: &mut PotionPool,
+        abilities: &Abilities,
         camera: Entity,
         cooldown: &mut AbilityCooldown,
+        ammo: &mut AbilityAmmo,
         cooldown_sheet: &CooldownSpritesheet,
+        font: &StandardFont,
+        layout: HudLayout,
+        player: Entity,
         position: Vec3,
         velocity: Velocity,
-        right: bool,
+        direction: Vec2,
+        power: f32,
         asset_server: &AssetServer,
     ) {
-        match self {
-            Self::Green => {
-                if cooldown.green.is_none() {
-                    let timer = Timer::from_seconds(0.75, TimerMode::Once);
-                    commands.entity(camera).with_children(|parent| {
-                        parent.spawn((
-                            Cooldown(timer.clone()),
-                            SpriteSheetBundle {
-                                texture_atlas: cooldown_sheet.0.clone(),
-                                transform: Transform::from_xyz(164., GreenPotion::ui_position(), -1.),
-                                ..default()
-                            },
-                        ));
-                    });
-                    GreenPotion::activate(commands, position, velocity, right, asset_server);
-                    cooldown.green = Some(timer);
-                }
-            },
-            Self::Purple => {
-                if cooldown.purple.is_none() {
-                    let timer = Timer::from_seconds(1.5, TimerMode::Once);
-                    commands.entity(camera).with_children(|parent| {
+        let Some(ability) = abilities.get(self.0) else { return };
+
+        if cooldown.is_ready(self.0) && ammo.take(self.0) {
+            let timer = Timer::from_seconds(ability.cooldown, TimerMode::Once);
+            let overlay = match layout {
+                HudLayout::Full => Vec2::new(164., ability.ui_position),
+                HudLayout::Compact => ability_slot(layout, self.0, ability.ui_position),
+            };
+            commands.entity(camera).with_children(|parent| {
+                parent
+                    .spawn((
+                        Cooldown(timer.clone()),
+                        CooldownOverlay(self.0),
+                        HudElement,
+                        SpriteSheetBundle {
+                            texture_atlas: cooldown_sheet.0.clone(),
+                            transform: Transform::from_translation(overlay.extend(layers::HUD)),
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
                         parent.spawn((
-                            Cooldown(timer.clone()),
-                            SpriteSheetBundle {
-                                texture_atlas: cooldown_sheet.0.clone(),
-                                transform: Transform::from_xyz(164., PurplePotion::ui_position(), -1.),
+                            CooldownText,
+                            Text2dBundle {
+                                text: Text::from_section(
+                                    "",
+                                    TextStyle {
+                                        font: font.0.clone(),
+                                        font_size: 12.0,
+                                        color: Color::WHITE,
+                                    },
+                                )
+                                .with_alignment(TextAlignment::Center),
+                                transform: Transform::from_xyz(0., 0., 0.1),
                                 ..default()
                             },
                         ));
                     });
-                    PurplePotion::activate(commands, position, velocity, right, asset_server);
-                    cooldown.purple = Some(timer);
-                }
-            }
+            });
+            (ability.activate)(commands, pool, player, position, velocity, direction, power, asset_server);
+            cooldown.timers.insert(self.0, timer);
         }
     }
 }
@@ -175,6 +642,72 @@ pub struct AbilityUi;
 #[derive(Component)]
 pub struct ActiveAbilityUi;
 
+#[derive(Component)]
+pub struct AbilityIcon(pub usize);
+
+/// Shows how much ammo an ability has left.
+#[derive(Component)]
+pub struct AmmoText(pub usize);
+
+fn reset_ammo(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    abilities: Res<Abilities>,
+    ui: Query<(), With<AbilityUi>>,
+) {
+    // The ability UI only exists during a run, so this is the start of a new one
+    if game_state.is_changed() && *game_state == GameState::Gameplay && ui.is_empty() {
+        commands.insert_resource(AbilityAmmo::full(&abilities));
+    }
+}
+
+/// The index in [`Abilities`] of the ability each run starts with.
+#[derive(Resource, Default)]
+pub struct StartingAbility(pub usize);
+
+impl StartingAbility {
+    pub fn step(&mut self, delta: i32, count: usize) {
+        if count > 0 {
+            self.0 = (self.0 as i32 + delta).rem_euclid(count as i32) as usize;
+        }
+    }
+}
+
+fn reset_active_ability(
+    game_state: Res<GameState>,
+    starting: Res<StartingAbility>,
+    mut active: ResMut<ActiveAbility>,
+    abilities: Res<Abilities>,
+    ui: Query<(), With<AbilityUi>>,
+) {
+    if abilities.is_empty() {
+        return;
+    }
+
+    // Same as the ammo, only at the start of a new run
+    if game_state.is_changed() && *game_state == GameState::Gameplay && ui.is_empty() {
+        *active = ActiveAbility(starting.0.min(abilities.len() - 1));
+    }
+}
+
+/// Where the icon for the ability at `index` sits in the HUD.
+fn ability_slot(layout: HudLayout, index: usize, ui_position: f32) -> Vec2 {
+    match layout {
+        HudLayout::Full => Vec2::new(208., ui_position),
+        // Lined up along the right end of the bar
+        HudLayout::Compact => Vec2::new(144. + 28. * index as f32, HudLayout::COMPACT_BAR_Y),
+    }
+}
+
+/// Where the highlight behind the active ability sits.
+fn active_slot(layout: HudLayout, index: usize, ui_position: f32) -> Vec2 {
+    let slot = ability_slot(layout, index, ui_position);
+    match layout {
+        HudLayout::Full => slot + Vec2::X * 8.,
+        HudLayout::Compact => slot,
+    }
+}
+
 fn spawn_ability_ui(
     mut commands: Commands,
     main_camera: Query<Entity, With<MainCamera>>,
@@ -182,37 +715,72 @@ fn spawn_ability_ui(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     game_state: Res<GameState>,
+    ui: Query<(), With<AbilityUi>>,
+    abilities: Res<Abilities>,
+    active: Res<ActiveAbility>,
+    font: Res<StandardFont>,
+    (show_hud, layout): (Res<ShowHud>, Res<HudLayout>),
 ) {
     let Ok(main_camera) = main_camera.get_single() else { return; };
-    if game_state.is_changed() && *game_state == GameState::Gameplay {
+    if game_state.is_changed() && *game_state == GameState::Gameplay && ui.is_empty() {
+        let (highlight_size, icon_scale, ammo_offset, ammo_size) = match *layout {
+            HudLayout::Full => (Vec2::new(64., 40.), 1., Vec2::new(20., -12.), 12.),
+            HudLayout::Compact => (Vec2::new(28., 24.), 0.75, Vec2::new(10., -8.), 8.),
+        };
+        let highlight = abilities
+            .get(active.0)
+            .map_or(Vec2::ZERO, |ability| active_slot(*layout, active.0, ability.ui_position));
+
         commands.entity(main_camera).with_children(|parent| {
             parent
-                .spawn(AbilityUi)
-                .insert(SpatialBundle::default())
+                .spawn((AbilityUi, HudElement))
+                .insert(SpatialBundle {
+                    visibility: show_hud.visibility(),
+                    ..default()
+                })
                 .with_children(|parent| {
                     parent
                         .spawn(ColorMesh2dBundle {
                             mesh: meshes
-                                .add(shape::Quad::new(Vec2::new(64., 40.)).into())
+                                .add(shape::Quad::new(highlight_size).into())
                                 .into(),
                             material: materials
                                 .add(ColorMaterial::from(Color::rgba(0.5, 0.5, 0.5, 0.5))),
-                            transform: Transform::from_xyz(216., GreenPotion::ui_position(), -2.),
+                            transform: Transform::from_translation(highlight.extend(layers::HUD_BACKGROUND)),
                             ..default()
                         })
                         .insert(ActiveAbilityUi);
 
-                    parent.spawn(SpriteBundle {
-                        texture: GreenPotion::ui_image(&asset_server),
-                        transform: Transform::from_xyz(208., GreenPotion::ui_position(), -1.),
-                        ..default()
-                    });
+                    for (index, ability) in abilities.iter() {
+                        let slot = ability_slot(*layout, index, ability.ui_position);
 
-                    parent.spawn(SpriteBundle {
-                        texture: PurplePotion::ui_image(&asset_server),
-                        transform: Transform::from_xyz(208., PurplePotion::ui_position(), -1.),
-                        ..default()
-                    });
+                        parent.spawn((
+                            AbilityIcon(index),
+                            SpriteBundle {
+                                texture: (ability.ui_image)(&asset_server),
+                                transform: Transform::from_translation(slot.extend(layers::HUD))
+                                    .with_scale(Vec3::splat(icon_scale)),
+                                ..default()
+                            },
+                        ));
+
+                        parent.spawn((
+                            AmmoText(index),
+                            Text2dBundle {
+                                text: Text::from_section(
+                                    ability.max_ammo.to_string(),
+                                    TextStyle {
+                                        font: font.0.clone(),
+                                        font_size: ammo_size,
+                                        color: Color::WHITE,
+                                    },
+                                )
+                                .with_alignment(TextAlignment::Center),
+                                transform: Transform::from_translation((slot + ammo_offset).extend(layers::HUD_TEXT)),
+                                ..default()
+                            },
+                        ));
+                    }
                 });
         });
     }
@@ -223,7 +791,7 @@ fn despawn_ability_ui(
     ui: Query<Entity, With<AbilityUi>>,
     game_state: Res<GameState>,
 ) {
-    if game_state.is_changed() && *game_state != GameState::Gameplay {
+    if game_state.is_changed() && !game_state.in_run() {
         let Ok(ui) = ui.get_single() else { return };
         commands.entity(ui).despawn_recursive();
     }
@@ -231,33 +799,25 @@ fn despawn_ability_ui(
 
 fn update_active_ability(
     mut active: ResMut<ActiveAbility>,
-    mut scroll_evr: EventReader<MouseWheel>,
-    keys: Res<Input<KeyCode>>,
+    abilities: Res<Abilities>,
+    mut input: ActionInput,
 ) {
-    let mut delta = 0.;
-    for ev in scroll_evr.iter() {
-        delta += ev.y;
-    }
-
-    if keys.just_pressed(KeyCode::W) {
-        delta += 1.;
-    }
-
-    if keys.just_pressed(KeyCode::S) {
-        delta -= 1.;
-    }
-
-    if delta > 0. {
-        active.add();
-    } else if delta < 0. {
-        active.subtract();
+    match input.switch_delta() {
+        1 => active.add(abilities.len()),
+        -1 => active.subtract(abilities.len()),
+        _ => {}
     }
 }
 
 #[derive(Resource, Default)]
 pub struct AbilityCooldown {
-    green: Option<Timer>,
-    purple: Option<Timer>,
+    timers: HashMap<usize, Timer>,
+}
+
+impl AbilityCooldown {
+    pub fn is_ready(&self, ability: usize) -> bool {
+        !self.timers.contains_key(&ability)
+    }
 }
 
 #[derive(Resource)]
@@ -266,68 +826,260 @@ pub struct CooldownSpritesheet(Handle<TextureAtlas>);
 #[derive(Component)]
 pub struct Cooldown(pub Timer);
 
+/// Shows the seconds left on the cooldown overlay it's a child of.
+#[derive(Component)]
+pub struct CooldownText;
+
+/// Which ability a cooldown overlay belongs to.
+#[derive(Component)]
+pub struct CooldownOverlay(pub usize);
+
+/// The fraction of an ability's cooldown given back when its potion misses every enemy.
+#[derive(Resource)]
+pub struct CooldownRefund(pub f32);
+
+/// Sent when a potion shatters, recording whether it hit an enemy.
+pub struct PotionLanded {
+    pub ability: usize,
+    pub hit: bool,
+}
+
+fn refund_missed_cooldowns(
+    mut landed: EventReader<PotionLanded>,
+    refund: Res<CooldownRefund>,
+    mut cooldown: ResMut<AbilityCooldown>,
+    mut overlays: Query<(&mut Cooldown, &CooldownOverlay)>,
+) {
+    for PotionLanded { ability, hit } in landed.iter() {
+        if *hit || refund.0 <= 0.0 {
+            continue;
+        }
+
+        let Some(timer) = cooldown.timers.get_mut(ability) else { continue };
+
+        let refunded = timer.duration().mul_f32(refund.0.min(1.0));
+        let elapsed = timer.elapsed() + refunded;
+        timer.set_elapsed(elapsed.min(timer.duration()));
+
+        for (mut overlay, CooldownOverlay(overlay_ability)) in overlays.iter_mut() {
+            if overlay_ability == ability {
+                overlay.0.set_elapsed(elapsed.min(overlay.0.duration()));
+            }
+        }
+    }
+}
+
 fn use_ability(
     commands: Commands,
-    camera: Query<Entity, With<MainCamera>>,
+    camera: Query<(Entity, &Camera, &GlobalTransform), With<MainCamera>>,
+    (windows, keyboard_aim): (Query<&Window, With<PrimaryWindow>>, Res<KeyboardAim>),
     mut cooldown: ResMut<AbilityCooldown>,
-    cooldown_sheet: Res<CooldownSpritesheet>,
-    keys: Res<Input<KeyCode>>,
-    buttons: Res<Input<MouseButton>>,
+    mut ammo: ResMut<AbilityAmmo>,
+    (cooldown_sheet, font, layout): (Res<CooldownSpritesheet>, Res<StandardFont>, Res<HudLayout>),
+    input: ActionInput,
+    mut charge: ResMut<ThrowCharge>,
+    time: Res<Time>,
     asset_server: Res<AssetServer>,
-    player: Query<(&Transform, &Velocity, &TextureAtlasSprite), With<Player>>,
+    player: Query<(Entity, &Transform, &Velocity, &TextureAtlasSprite), With<Player>>,
     active_ability: Res<ActiveAbility>,
+    abilities: Res<Abilities>,
     game_state: Res<GameState>,
+    (potions, max_potions): (Query<(), With<Potion>>, Res<MaxPotions>),
+    mut pool: ResMut<PotionPool>,
 ) {
     if *game_state != GameState::Gameplay {
+        *charge = ThrowCharge::default();
         return;
     };
 
-    let Ok(camera) = camera.get_single() else { return };
+    let Ok((camera, camera_settings, camera_transform)) = camera.get_single() else { return };
 
-    if keys.just_pressed(KeyCode::E) || buttons.just_pressed(MouseButton::Left) {
-        let Ok((transform, velocity, sprite)) = player.get_single() else { return };
+    let thrown = abilities.get(active_ability.0).map_or(true, |ability| ability.thrown);
 
-        let right = !sprite.flip_x;
+    // Switching abilities cancels the charge
+    if charge.ability.is_some() && charge.ability != Some(active_ability.0) {
+        *charge = ThrowCharge::default();
+    }
 
-        let position = if right {
-            transform.translation + Vec3::X * 12.
-        } else {
-            transform.translation - Vec3::X * 12.
-        };
+    let power = if !thrown {
+        // Abilities that aren't thrown can't be charged
+        if !input.throw_just_pressed() {
+            return;
+        }
+        1.0
+    } else if input.throw_just_pressed() {
+        charge.charge = 0.;
+        charge.ability = Some(active_ability.0);
+        return;
+    } else if input.throw_pressed() {
+        if charge.ability.is_some() {
+            charge.charge = (charge.charge + time.delta_seconds() / ThrowCharge::TIME).min(1.0);
+        }
+        return;
+    } else if charge.ability.is_some() {
+        // Released, so throw with whatever was charged
+        let power = charge.power();
+        *charge = ThrowCharge::default();
+        power
+    } else {
+        return;
+    };
 
-        active_ability.activate(commands, camera, &mut *cooldown, &cooldown_sheet, position, *velocity, right, &*asset_server);
+    if thrown && potions.iter().count() >= max_potions.0 {
+        return;
     }
+
+    let Ok((player, transform, velocity, sprite)) = player.get_single() else { return };
+
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| cursor_world_position(window, camera_settings, camera_transform));
+
+    let launch = abilities.get(active_ability.0).map_or(DEFAULT_LAUNCH, |ability| ability.launch_velocity);
+    let (position, direction) = aim(transform, sprite, cursor, &keyboard_aim, launch);
+
+    active_ability.activate(commands, &mut pool, &abilities, camera, &mut *cooldown, &mut *ammo, &cooldown_sheet, &font, *layout, player, position, *velocity, direction, power, &*asset_server);
 }
 
 fn update_ability_ui(
     mut ui: Query<&mut Transform, With<ActiveAbilityUi>>,
+    mut icons: Query<(&AbilityIcon, &mut Sprite)>,
+    mut ammo_texts: Query<(&AmmoText, &mut Text)>,
     active: Res<ActiveAbility>,
+    abilities: Res<Abilities>,
+    ammo: Res<AbilityAmmo>,
+    layout: Res<HudLayout>,
 ) {
+    for (icon, mut sprite) in icons.iter_mut() {
+        // Dim abilities that are out of ammo
+        sprite.color = if ammo.get(icon.0) > 0 {
+            Color::WHITE
+        } else {
+            Color::rgba(0.4, 0.4, 0.4, 0.6)
+        };
+    }
+
+    for (ammo_text, mut text) in ammo_texts.iter_mut() {
+        let count = ammo.get(ammo_text.0).to_string();
+        if text.sections[0].value != count {
+            text.sections[0].value = count;
+        }
+    }
+
     let Ok(mut ui) = ui.get_single_mut() else { return };
+    let Some(ability) = abilities.get(active.0) else { return };
 
-    ui.translation.y = active.ui_position();
+    let slot = active_slot(*layout, active.0, ability.ui_position);
+    ui.translation.x = slot.x;
+    ui.translation.y = slot.y;
 }
 
 const POTION_GRAVITY: f32 = 9.81 * 175f32;
 
-fn update_potion_gravity(mut potions: Query<&mut Velocity, With<Potion>>, time: Res<Time>) {
+/// How quickly thrown potions lose their horizontal speed, per second.
+/// Zero keeps their speed until they hit something; higher values make long throws drop off sooner.
+#[derive(Resource, Default)]
+pub struct PotionDrag(pub f32);
+
+impl PotionDrag {
+    /// Slows a potion's velocity over `seconds`, the same way whatever the frame rate.
+    fn apply(&self, velocity: &mut Vec2, seconds: f32) {
+        velocity.x *= (-self.0 * seconds).exp();
+    }
+}
+
+fn update_potion_gravity(
+    mut potions: Query<&mut Velocity, With<Potion>>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    hit_stop: Res<HitStop>,
+    drag: Res<PotionDrag>,
+) {
+    if *game_state != GameState::Gameplay || hit_stop.active() {
+        return;
+    }
+
     for mut velocity in potions.iter_mut() {
         velocity.linvel.y -= POTION_GRAVITY * time.delta_seconds();
+        drag.apply(&mut velocity.linvel, time.delta_seconds());
+    }
+}
+
+/// Sent when an ability's cooldown finishes.
+pub struct AbilityReady(pub usize);
+
+fn update_cooldowns(
+    mut cooldown: ResMut<AbilityCooldown>,
+    time: Res<Time>,
+    mut ready: EventWriter<AbilityReady>,
+    game_state: Res<GameState>,
+) {
+    // Cooldowns wait out pauses like the game timer does, so pausing can't be used to recharge
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    cooldown.timers.retain(|ability, timer| {
+        timer.tick(time.delta());
+        if timer.finished() {
+            ready.send(AbilityReady(*ability));
+        }
+        !timer.finished()
+    });
+}
+
+/// Cooldowns only run during gameplay, so any left when a run ends are dropped rather than carried into the next.
+fn clear_cooldowns(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    mut cooldown: ResMut<AbilityCooldown>,
+    overlays: Query<Entity, With<CooldownOverlay>>,
+) {
+    if !game_state.is_changed() || game_state.in_run() {
+        return;
+    }
+
+    cooldown.timers.clear();
+    for overlay in overlays.iter() {
+        commands.entity(overlay).despawn_recursive();
     }
 }
 
-fn update_cooldowns(mut cooldown: ResMut<AbilityCooldown>, time: Res<Time>) {
-    if let Some(green) = &mut cooldown.green {
-        green.tick(time.delta());
-        if green.finished() {
-            cooldown.green = None;
+#[derive(Component)]
+pub struct ReadyFlash(Timer);
+
+fn flash_ready_abilities(
+    mut commands: Commands,
+    mut ready: EventReader<AbilityReady>,
+    icons: Query<(Entity, &AbilityIcon)>,
+) {
+    for AbilityReady(ability) in ready.iter() {
+        for (entity, icon) in icons.iter() {
+            if icon.0 == *ability {
+                commands
+                    .entity(entity)
+                    .insert(ReadyFlash(Timer::from_seconds(0.25, TimerMode::Once)));
+            }
         }
     }
+}
+
+fn update_ready_flash(
+    mut commands: Commands,
+    mut icons: Query<(Entity, &mut ReadyFlash, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut transform) in icons.iter_mut() {
+        flash.0.tick(time.delta());
 
-    if let Some(purple) = &mut cooldown.purple {
-        purple.tick(time.delta());
-        if purple.finished() {
-            cooldown.purple = None;
+        // Pop the icon up and back down
+        let pop = (flash.0.percent() * std::f32::consts::PI).sin();
+        transform.scale = Vec3::splat(1.0 + 0.3 * pop);
+
+        if flash.0.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<ReadyFlash>();
         }
     }
 }
@@ -346,3 +1098,515 @@ pub struct SpeedEffect {
 pub struct DamageEffect {
     pub multiplier: f32,
 }
+
+/// Damage dealt over time, once every time the timer finishes.
+#[derive(Component)]
+pub struct BurnEffect {
+    pub ticks_remaining: u32,
+    pub damage_per_tick: i32,
+    pub timer: Timer,
+}
+
+impl Default for BurnEffect {
+    fn default() -> Self {
+        Self {
+            ticks_remaining: 3,
+            damage_per_tick: -1,
+            timer: Timer::from_seconds(0.5, TimerMode::Repeating),
+        }
+    }
+}
+
+/// How hard, in pixels per second, a potion knocks back what it hits.
+/// Potions scale this, so heavier ones hit harder.
+pub const POTION_KNOCKBACK: f32 = 250.;
+
+/// Something a potion does to whatever it hits.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PotionEffect {
+    /// Takes away health and flashes the target.
+    Damage(i32),
+    /// Scales how fast the target moves.
+    Slow(f32),
+    /// Stops the target from moving on its own.
+    Freeze,
+    /// Takes away health a tick at a time, see [`BurnEffect`].
+    DamageOverTime {
+        ticks: u32,
+        damage_per_tick: i32,
+        interval: f32,
+    },
+    /// Pushes the target away from where the potion hit.
+    Knockback(f32),
+    /// Scales the damage the target deals on contact.
+    DamageMultiplier(f32),
+    /// Boosts the player for a number of seconds.
+    Buff(BuffKind, f32),
+}
+
+impl PotionEffect {
+    pub fn kind(&self) -> EffectKind {
+        match self {
+            PotionEffect::Damage(_) => EffectKind::Damage,
+            PotionEffect::Slow(_) => EffectKind::Slow,
+            PotionEffect::Freeze => EffectKind::Freeze,
+            PotionEffect::DamageOverTime { .. } => EffectKind::DamageOverTime,
+            PotionEffect::Knockback(_) => EffectKind::Knockback,
+            PotionEffect::DamageMultiplier(_) => EffectKind::DamageMultiplier,
+            PotionEffect::Buff(..) => EffectKind::Buff,
+        }
+    }
+
+    /// The effect at half strength, for targets that resist it.
+    pub fn resisted(self) -> Self {
+        match self {
+            // Resisting still hurts a little
+            PotionEffect::Damage(amount) => PotionEffect::Damage((amount / 2).max(1)),
+            PotionEffect::Slow(multiplier) => PotionEffect::Slow(1. + (multiplier - 1.) / 2.),
+            PotionEffect::Freeze => PotionEffect::Slow(0.5),
+            PotionEffect::DamageOverTime { ticks, damage_per_tick, interval } => {
+                PotionEffect::DamageOverTime {
+                    ticks: (ticks + 1) / 2,
+                    damage_per_tick,
+                    interval,
+                }
+            }
+            PotionEffect::Knockback(strength) => PotionEffect::Knockback(strength / 2.),
+            PotionEffect::DamageMultiplier(multiplier) => {
+                PotionEffect::DamageMultiplier(1. + (multiplier - 1.) / 2.)
+            }
+            PotionEffect::Buff(kind, seconds) => PotionEffect::Buff(kind, seconds / 2.),
+        }
+    }
+
+    fn apply(
+        &self,
+        commands: &mut Commands,
+        target: Entity,
+        direction: Vec2,
+        velocity: Option<&mut Velocity>,
+    ) {
+        match *self {
+            PotionEffect::Damage(amount) => {
+                insert_if_alive(commands, target, (HealthEffect { amount: -amount }, DamageFlash::default()));
+            }
+            PotionEffect::Slow(multiplier) => {
+                insert_if_alive(commands, target, SpeedEffect { multiplier });
+            }
+            PotionEffect::Freeze => {
+                insert_if_alive(commands, target, SpeedEffect { multiplier: 0. });
+            }
+            PotionEffect::DamageOverTime { ticks, damage_per_tick, interval } => {
+                // Replacing an existing burn refreshes it instead of stacking
+                insert_if_alive(
+                    commands,
+                    target,
+                    BurnEffect {
+                        ticks_remaining: ticks,
+                        damage_per_tick: -damage_per_tick,
+                        timer: Timer::from_seconds(interval, TimerMode::Repeating),
+                    },
+                );
+            }
+            PotionEffect::Knockback(strength) => {
+                if let Some(velocity) = velocity {
+                    // A little lift, so hits from above don't just push into the ground
+                    let direction = (direction + Vec2::Y * 0.5).normalize_or_zero();
+                    velocity.linvel += direction * strength;
+                }
+            }
+            PotionEffect::DamageMultiplier(multiplier) => {
+                insert_if_alive(commands, target, DamageEffect { multiplier });
+            }
+            PotionEffect::Buff(kind, seconds) => {
+                insert_if_alive(
+                    commands,
+                    target,
+                    PlayerBuff {
+                        kind,
+                        timer: Timer::from_seconds(seconds, TimerMode::Once),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Inserts a bundle only if the entity still exists once commands are applied.
+/// Effects can land on an enemy in the same frame it dies, and inserting into a despawned entity panics.
+pub fn insert_if_alive(commands: &mut Commands, entity: Entity, bundle: impl Bundle) {
+    commands.add(move |world: &mut World| {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            entity.insert(bundle);
+        }
+    });
+}
+
+/// The kinds of [`PotionEffect`], regardless of their strength.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EffectKind {
+    Damage,
+    Slow,
+    Freeze,
+    DamageOverTime,
+    Knockback,
+    DamageMultiplier,
+    Buff,
+}
+
+impl EffectKind {
+    pub const ALL: [Self; 7] = [
+        Self::Damage,
+        Self::Slow,
+        Self::Freeze,
+        Self::DamageOverTime,
+        Self::Knockback,
+        Self::DamageMultiplier,
+        Self::Buff,
+    ];
+
+    /// The name used to refer to the effect in LDtk.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Damage => "Damage",
+            Self::Slow => "Slow",
+            Self::Freeze => "Freeze",
+            Self::DamageOverTime => "DamageOverTime",
+            Self::Knockback => "Knockback",
+            Self::DamageMultiplier => "DamageMultiplier",
+            Self::Buff => "Buff",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Effects that don't work as well, or at all, on an enemy.
+#[derive(Component, Default)]
+pub struct Resistances {
+    /// Effects that are skipped entirely.
+    pub immune: Vec<EffectKind>,
+    /// Effects that are applied at half strength.
+    pub resisted: Vec<EffectKind>,
+}
+
+impl Resistances {
+    /// Parses comma separated effect names, like "DamageOverTime, Knockback".
+    pub fn parse_kinds(names: &str) -> Vec<EffectKind> {
+        names
+            .split(',')
+            .filter_map(|name| EffectKind::from_name(name.trim()))
+            .collect()
+    }
+}
+
+/// Shatters potions of ability `A` when they hit something, applying its effects to what they hit.
+fn potion_hits<A: Ability + Component>(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    potions: Query<(Entity, &Transform), With<A>>,
+    mut targets: Query<
+        (&Transform, Option<&mut Velocity>, Option<&Resistances>, Option<&mut Stunned>),
+        Without<Potion>,
+    >,
+    enemies: Query<(), With<Enemy>>,
+    players: Query<(), With<Player>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut landed: EventWriter<PotionLanded>,
+    abilities: Res<Abilities>,
+    quality: Res<ParticleQuality>,
+    mut rng: ResMut<GameRng>,
+    font: Res<StandardFont>,
+    mut hit_stop: ResMut<HitStop>,
+    mut pool: ResMut<PotionPool>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(a, b, flags) = collision_event else { continue };
+
+        if *flags & CollisionEventFlags::SENSOR == CollisionEventFlags::SENSOR {
+            continue;
+        }
+
+        let (entity, transform, other) = if let Ok((entity, transform)) = potions.get(*a) {
+            (entity, transform, *b)
+        } else if let Ok((entity, transform)) = potions.get(*b) {
+            (entity, transform, *a)
+        } else {
+            continue;
+        };
+
+        let hit = enemies.contains(other);
+        if hit {
+            hit_stop.trigger();
+        }
+        let effects = if players.contains(other) {
+            A::player_effects()
+        } else if hit {
+            A::effects()
+        } else {
+            Vec::new()
+        };
+
+        // Damage waits for the splash to shatter, so it lands with the visual
+        let mut delayed_damage = 0;
+
+        if let Ok((target_transform, mut velocity, resistances, stunned)) = targets.get_mut(other) {
+            let direction = (target_transform.translation - transform.translation)
+                .truncate()
+                .normalize_or_zero();
+
+            let mut blocked = false;
+
+            for effect in effects {
+                let effect = match resistances {
+                    Some(resistances) if resistances.immune.contains(&effect.kind()) => {
+                        blocked = true;
+                        continue;
+                    }
+                    Some(resistances) if resistances.resisted.contains(&effect.kind()) => {
+                        effect.resisted()
+                    }
+                    _ => effect,
+                };
+
+                if let PotionEffect::Damage(amount) = effect {
+                    delayed_damage += amount;
+                    continue;
+                }
+
+                effect.apply(&mut commands, other, direction, velocity.as_deref_mut());
+            }
+
+            // Gives knockback a moment to carry the enemy before it starts moving again,
+            // so there's nothing to wait for when it can't be knocked back
+            let knockback_immune =
+                resistances.map_or(false, |resistances| resistances.immune.contains(&EffectKind::Knockback));
+            if hit && !knockback_immune {
+                match stunned {
+                    Some(mut stunned) => stunned.timer.reset(),
+                    None => insert_if_alive(&mut commands, other, Stunned::default()),
+                }
+            }
+
+            if blocked {
+                commands.spawn((
+                    FloatingText,
+                    Lifetime::from_seconds(1.0),
+                    SimpleMotion {
+                        velocity: Vec2::new(0., -16.),
+                        ..default()
+                    },
+                    Text2dBundle {
+                        text: Text::from_section(
+                            "Blocked",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 12.0,
+                                color: Color::GRAY,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_translation(
+                            (target_transform.translation.truncate() + Vec2::Y * 32.).extend(layers::OVERLAY),
+                        ),
+                        ..default()
+                    },
+                ));
+            }
+        }
+
+        landed.send(PotionLanded {
+            ability: abilities.index_of::<A>(),
+            hit,
+        });
+
+        pool.release::<A>(&mut commands, entity);
+        let splash = spawn_splash(
+            &mut commands,
+            A::splash_image(&asset_server, &mut texture_atlases),
+            *transform,
+            A::splash_color(),
+            *quality,
+            &mut rng,
+        );
+
+        if delayed_damage > 0 {
+            commands.entity(splash).insert(OnAnimationEnd::new(move |world: &mut World| {
+                // The target may have died or despawned since the potion hit
+                let Some(mut target) = world.get_entity_mut(other) else { return };
+                target.insert((HealthEffect { amount: -delayed_damage }, DamageFlash::default()));
+            }));
+        }
+    }
+}
+
+const PREVIEW_DOTS: usize = 24;
+const PREVIEW_STEP: f32 = 1. / 30.;
+
+#[derive(Component)]
+pub struct TrajectoryDot;
+
+/// Shows the arc a potion would follow while the throw button is held.
+fn update_trajectory_preview(
+    mut commands: Commands,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    charge: Res<ThrowCharge>,
+    player: Query<(&Transform, &Velocity, &TextureAtlasSprite), With<Player>>,
+    mut dots: Query<
+        (&mut Transform, &mut Sprite, &mut Visibility),
+        (With<TrajectoryDot>, Without<Player>),
+    >,
+    cooldown: Res<AbilityCooldown>,
+    ammo: Res<AbilityAmmo>,
+    active_ability: Res<ActiveAbility>,
+    abilities: Res<Abilities>,
+    game_state: Res<GameState>,
+    rapier_context: Res<RapierContext>,
+    keyboard_aim: Res<KeyboardAim>,
+    drag: Res<PotionDrag>,
+) {
+    let thrown = abilities.get(active_ability.0).map_or(false, |ability| ability.thrown);
+    let aiming = *game_state == GameState::Gameplay && thrown && charge.ability.is_some();
+
+    let mut points = Vec::with_capacity(PREVIEW_DOTS);
+
+    if aiming {
+        if let (Ok((transform, velocity, sprite)), Ok((camera, camera_transform))) =
+            (player.get_single(), camera.get_single())
+        {
+            let cursor = windows
+                .get_single()
+                .ok()
+                .and_then(|window| cursor_world_position(window, camera, camera_transform));
+
+            let launch = abilities.get(active_ability.0).map_or(DEFAULT_LAUNCH, |ability| ability.launch_velocity);
+            let (position, direction) = aim(transform, sprite, cursor, &keyboard_aim, launch);
+
+            let mut position = position.truncate();
+            // The arc grows as the throw charges
+            let mut velocity = launch_velocity(launch, direction, *velocity, charge.power());
+
+            let filter = QueryFilter::new().groups(CollisionGroups::new(Group::GROUP_5, Group::GROUP_1));
+
+            for _ in 0..PREVIEW_DOTS {
+                velocity.y -= POTION_GRAVITY * PREVIEW_STEP;
+                drag.apply(&mut velocity, PREVIEW_STEP);
+                let step = velocity * PREVIEW_STEP;
+
+                // Stop at the first wall the potion would hit
+                if let Some((_, toi)) = rapier_context.cast_ray(position, step, 1.0, true, filter) {
+                    points.push(position + step * toi);
+                    break;
+                }
+
+                position += step;
+                points.push(position);
+            }
+        }
+    }
+
+    let color = if cooldown.is_ready(active_ability.0) && ammo.get(active_ability.0) > 0 {
+        Color::rgba(1.0, 1.0, 1.0, 0.6)
+    } else {
+        Color::rgba(1.0, 0.2, 0.2, 0.6)
+    };
+
+    // Reuse the dots that were already spawned, only spawning more when the arc gets longer
+    let mut points = points.into_iter();
+
+    for (mut transform, mut sprite, mut visibility) in dots.iter_mut() {
+        if let Some(point) = points.next() {
+            transform.translation = point.extend(layers::OVERLAY);
+            sprite.color = color;
+            *visibility = Visibility::Inherited;
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+
+    for point in points {
+        commands.spawn((
+            TrajectoryDot,
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(point.extend(layers::OVERLAY)),
+                ..default()
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    #[test]
+    fn insert_if_alive_skips_despawned_entities() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+
+        let mut queue = CommandQueue::default();
+        {
+            let mut commands = Commands::new(&mut queue, &world);
+            commands.entity(entity).despawn();
+            insert_if_alive(&mut commands, entity, Pooled);
+        }
+        queue.apply(&mut world);
+
+        assert!(world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn cycling_abilities_wraps_around() {
+        for count in 1..=5 {
+            let mut active = ActiveAbility(count - 1);
+            for _ in 0..count {
+                active.add(count);
+            }
+            assert_eq!(active.0, count - 1);
+
+            for _ in 0..count {
+                active.subtract(count);
+            }
+            assert_eq!(active.0, count - 1);
+        }
+    }
+
+    #[derive(Component)]
+    struct TestPotion;
+
+    #[test]
+    fn pooled_throws_reuse_entities() {
+        let mut world = World::new();
+        let mut pool = PotionPool::default();
+        let mut throw = |world: &mut World| {
+            let mut queue = CommandQueue::default();
+            {
+                let mut commands = Commands::new(&mut queue, world);
+                let potion = pool.spawn(&mut commands, (Potion, TestPotion));
+                pool.release::<TestPotion>(&mut commands, potion);
+            }
+            queue.apply(world);
+        };
+
+        throw(&mut world);
+        let entities = world.entities().len();
+
+        for _ in 0..100 {
+            throw(&mut world);
+        }
+
+        assert_eq!(world.entities().len(), entities);
+        assert_eq!(pool.free.len(), 1);
+    }
+}
@@ -1,9 +1,5 @@
 use super::*;
 
-use crate::animator::*;
-
-use bevy_rapier2d::rapier::prelude::CollisionEventFlags;
-
 #[derive(Component)]
 pub struct PurplePotion;
 
@@ -25,69 +21,65 @@ impl Ability for PurplePotion {
         -80.
     }
 
+    fn name() -> &'static str {
+        "Purple"
+    }
+
+    fn max_ammo() -> u32 {
+        5
+    }
+
+    fn cooldown() -> f32 {
+        1.5
+    }
+
+    fn splash_color() -> Color {
+        Color::rgb(0.7, 0.3, 0.9)
+    }
+
+    fn launch_velocity() -> Vec2 {
+        Vec2::new(400., 200.)
+    }
+
+    fn effects() -> Vec<PotionEffect> {
+        vec![
+            PotionEffect::Damage(2),
+            PotionEffect::DamageMultiplier(3.0),
+            PotionEffect::Knockback(POTION_KNOCKBACK * 2.),
+        ]
+    }
+
+    fn player_effects() -> Vec<PotionEffect> {
+        vec![PotionEffect::Damage(2)]
+    }
+
     fn activate(
         mut commands: Commands,
+        pool: &mut PotionPool,
+        _player: Entity,
         position: Vec3,
         velocity: Velocity,
-        right: bool,
+        direction: Vec2,
+        power: f32,
         asset_server: &AssetServer,
     ) {
-        let new_velocity =
-            Vec2::new(if right { 400. } else { -400. }, 200.) + velocity.linvel * 0.5;
-
-        commands.spawn((
-            PotionBundle::default(),
-            PurplePotion,
-            SpriteBundle {
-                texture: asset_server.load("images/abilities/purple_small.png"),
-                transform: Transform::from_translation(position),
-                ..default()
-            },
-            Velocity {
-                linvel: new_velocity,
-                angvel: 10.0,
-            },
-        ));
-    }
-}
-
-pub fn checks(
-    mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
-    potions: Query<(Entity, &Transform), With<PurplePotion>>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-) {
-    for collision_event in collision_events.iter() {
-        let CollisionEvent::Started(a, b, flags) = collision_event else { continue };
-
-        if *flags & CollisionEventFlags::SENSOR == CollisionEventFlags::SENSOR {
-            continue;
-        }
-
-        let (entity, transform, other) = if let Ok((entity, transform)) = potions.get(*a) {
-            (entity, transform, *b)
-        } else if let Ok((entity, transform)) = potions.get(*b) {
-            (entity, transform, *a)
-        } else {
-            continue;
-        };
+        let new_velocity = launch_velocity(Self::launch_velocity(), direction, velocity, power);
 
-        commands
-            .entity(other)
-            .insert(HealthEffect { amount: -2 })
-            .insert(DamageEffect { multiplier: 3.0 })
-            .insert(DamageFlash::default());
-        commands.entity(entity).despawn();
-        commands.spawn((
-            SpriteSheetBundle {
-                texture_atlas: PurplePotion::splash_image(&asset_server, &mut texture_atlases),
-                transform: *transform,
-                ..default()
-            },
-            AnimationIndices { first: 0, last: 6 },
-            AnimationTimer(Timer::from_seconds(1. / 12., TimerMode::Repeating)),
-            Destruct,
-        ));
+        pool.spawn(
+            &mut commands,
+            (
+                PotionBundle::default(),
+                PurplePotion,
+                SpriteBundle {
+                    texture: asset_server.load("images/abilities/purple_small.png"),
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+                Velocity {
+                    linvel: new_velocity,
+                    angvel: 10.0,
+                },
+            ),
+        );
     }
 }
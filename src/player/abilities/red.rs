@@ -0,0 +1,85 @@
+use super::*;
+
+#[derive(Component)]
+pub struct RedPotion;
+
+impl Ability for RedPotion {
+    fn splash_image(
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Handle<TextureAtlas> {
+        let texture = asset_server.load("images/abilities/red_splash.png");
+        let texture_atlas = TextureAtlas::from_grid(texture, Vec2::new(32., 32.), 3, 3, None, None);
+        texture_atlases.add(texture_atlas)
+    }
+
+    fn ui_image(asset_server: &AssetServer) -> Handle<Image> {
+        asset_server.load("images/abilities/red.png")
+    }
+
+    fn ui_position() -> f32 {
+        -40.
+    }
+
+    fn name() -> &'static str {
+        "Red"
+    }
+
+    fn max_ammo() -> u32 {
+        4
+    }
+
+    fn cooldown() -> f32 {
+        2.0
+    }
+
+    fn splash_color() -> Color {
+        Color::rgb(1.0, 0.4, 0.2)
+    }
+
+    fn launch_velocity() -> Vec2 {
+        Vec2::new(400., 200.)
+    }
+
+    fn effects() -> Vec<PotionEffect> {
+        vec![PotionEffect::DamageOverTime {
+            ticks: 3,
+            damage_per_tick: 1,
+            interval: 0.5,
+        }]
+    }
+
+    fn player_effects() -> Vec<PotionEffect> {
+        vec![PotionEffect::Damage(1)]
+    }
+
+    fn activate(
+        mut commands: Commands,
+        pool: &mut PotionPool,
+        _player: Entity,
+        position: Vec3,
+        velocity: Velocity,
+        direction: Vec2,
+        power: f32,
+        asset_server: &AssetServer,
+    ) {
+        let new_velocity = launch_velocity(Self::launch_velocity(), direction, velocity, power);
+
+        pool.spawn(
+            &mut commands,
+            (
+                PotionBundle::default(),
+                RedPotion,
+                SpriteBundle {
+                    texture: asset_server.load("images/abilities/red_small.png"),
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+                Velocity {
+                    linvel: new_velocity,
+                    angvel: 10.0,
+                },
+            ),
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::*};
+use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
+
+use crate::{
+    player::PlayerDamageSensor,
+    score::{ScoreEvent, PICKUP_POINTS},
+    world::field_value,
+    BonusTime, TimeBonus,
+};
+
+use super::{Abilities, AbilityAmmo};
+
+/// A pickup that refills the ammo of the ability named by its "Ability" field in LDtk.
+#[derive(Component)]
+pub struct PotionRefill {
+    pub ability: String,
+}
+
+#[derive(Bundle)]
+pub struct PotionRefillBundle {
+    pub refill: PotionRefill,
+    pub sensor: Sensor,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_events: ActiveEvents,
+    pub texture: Handle<Image>,
+    pub sprite: Sprite,
+}
+
+impl LdtkEntity for PotionRefillBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        asset_server: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let ability = match field_value(&entity_instance.field_instances, "Ability") {
+            Some(FieldValue::String(Some(ability))) => ability.clone(),
+            _ => "Green".to_owned(),
+        };
+
+        Self {
+            texture: asset_server.load(format!("images/abilities/{}_small.png", ability.to_lowercase())),
+            refill: PotionRefill { ability },
+            sensor: Sensor,
+            collider: Collider::ball(8.0),
+            collision_groups: CollisionGroups::new(Group::GROUP_5, Group::GROUP_5),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::default(),
+        }
+    }
+}
+
+pub fn checks(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    refills: Query<&PotionRefill>,
+    player_sensors: Query<(), With<PlayerDamageSensor>>,
+    abilities: Res<Abilities>,
+    mut ammo: ResMut<AbilityAmmo>,
+    mut score: EventWriter<ScoreEvent>,
+    bonus: Res<BonusTime>,
+    mut time_bonus: EventWriter<TimeBonus>,
+) {
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(a, b, flags) = collision_event else { continue };
+
+        if *flags & CollisionEventFlags::SENSOR != CollisionEventFlags::SENSOR {
+            continue;
+        }
+
+        let (entity, other) = if refills.contains(*a) {
+            (*a, *b)
+        } else if refills.contains(*b) {
+            (*b, *a)
+        } else {
+            continue;
+        };
+
+        if !player_sensors.contains(other) {
+            continue;
+        }
+
+        let Ok(refill) = refills.get(entity) else { continue };
+
+        match abilities.index_by_name(&refill.ability) {
+            Some(ability) => ammo.refill(ability, &abilities),
+            None => warn!("PotionRefill for unknown ability {}", refill.ability),
+        }
+
+        commands.entity(entity).despawn_recursive();
+        score.send(ScoreEvent(PICKUP_POINTS));
+
+        if !bonus.pickup.is_zero() {
+            time_bonus.send(TimeBonus(bonus.pickup));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+use crate::player::{BuffKind, PlayerBuff};
+
+/// A potion the player drinks instead of throwing, making them jump higher for a while.
+#[derive(Component)]
+pub struct YellowPotion;
+
+impl Ability for YellowPotion {
+    fn splash_image(
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Handle<TextureAtlas> {
+        let texture = asset_server.load("images/abilities/yellow_splash.png");
+        let texture_atlas = TextureAtlas::from_grid(texture, Vec2::new(32., 32.), 3, 3, None, None);
+        texture_atlases.add(texture_atlas)
+    }
+
+    fn ui_image(asset_server: &AssetServer) -> Handle<Image> {
+        asset_server.load("images/abilities/yellow.png")
+    }
+
+    fn ui_position() -> f32 {
+        0.
+    }
+
+    fn name() -> &'static str {
+        "Yellow"
+    }
+
+    fn max_ammo() -> u32 {
+        3
+    }
+
+    fn cooldown() -> f32 {
+        8.0
+    }
+
+    fn thrown() -> bool {
+        false
+    }
+
+    fn activate(
+        mut commands: Commands,
+        _pool: &mut PotionPool,
+        player: Entity,
+        _position: Vec3,
+        _velocity: Velocity,
+        _direction: Vec2,
+        _power: f32,
+        _asset_server: &AssetServer,
+    ) {
+        commands.entity(player).insert(PlayerBuff {
+            kind: BuffKind::Jump(1.25),
+            timer: Timer::from_seconds(4.0, TimerMode::Once),
+        });
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseWheel},
+        ButtonState,
+    },
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{gamepad::GamepadInput, settings::Choice};
+
+/// Something the player can do that's bound to a key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Jump,
+    Left,
+    Right,
+    Crouch,
+    Throw,
+    NextAbility,
+    PrevAbility,
+    Dash,
+    AimUp,
+    AimDown,
+    ClimbUp,
+    ClimbDown,
+}
+
+impl Action {
+    pub const ALL: [Self; 12] = [
+        Self::Jump,
+        Self::Left,
+        Self::Right,
+        Self::Crouch,
+        Self::Throw,
+        Self::NextAbility,
+        Self::PrevAbility,
+        Self::Dash,
+        Self::AimUp,
+        Self::AimDown,
+        Self::ClimbUp,
+        Self::ClimbDown,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Jump => "Jump",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Crouch => "Crouch",
+            Self::Throw => "Throw",
+            Self::NextAbility => "Next Ability",
+            Self::PrevAbility => "Previous Ability",
+            Self::Dash => "Dash",
+            Self::AimUp => "Aim Up",
+            Self::AimDown => "Aim Down",
+            Self::ClimbUp => "Climb Up",
+            Self::ClimbDown => "Climb Down",
+        }
+    }
+}
+
+/// The key bound to each action.
+#[derive(Resource, PartialEq)]
+pub struct InputConfig(HashMap<Action, KeyCode>);
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self(HashMap::from_iter([
+            (Action::Jump, KeyCode::Space),
+            (Action::Left, KeyCode::A),
+            (Action::Right, KeyCode::D),
+            (Action::Crouch, KeyCode::LControl),
+            (Action::Throw, KeyCode::E),
+            (Action::NextAbility, KeyCode::W),
+            (Action::PrevAbility, KeyCode::S),
+            (Action::Dash, KeyCode::LShift),
+            (Action::AimUp, KeyCode::Up),
+            (Action::AimDown, KeyCode::Down),
+            (Action::ClimbUp, KeyCode::Up),
+            (Action::ClimbDown, KeyCode::Down),
+        ]))
+    }
+}
+
+impl InputConfig {
+    /// The default bindings mirrored to the other side of the keyboard,
+    /// for players who hold the mouse in their left hand.
+    pub fn southpaw() -> Self {
+        Self(HashMap::from_iter([
+            (Action::Jump, KeyCode::Space),
+            (Action::Left, KeyCode::J),
+            (Action::Right, KeyCode::L),
+            (Action::Crouch, KeyCode::RControl),
+            (Action::Throw, KeyCode::U),
+            (Action::NextAbility, KeyCode::I),
+            (Action::PrevAbility, KeyCode::K),
+            (Action::Dash, KeyCode::RShift),
+            (Action::AimUp, KeyCode::Up),
+            (Action::AimDown, KeyCode::Down),
+            (Action::ClimbUp, KeyCode::Up),
+            (Action::ClimbDown, KeyCode::Down),
+        ]))
+    }
+
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.0.get(&action).copied()
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.0.insert(action, key);
+    }
+
+    pub fn pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        self.key(action).map_or(false, |key| keys.pressed(key))
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        self.key(action).map_or(false, |key| keys.just_pressed(key))
+    }
+}
+
+/// Everything the player can press during gameplay, read through the bindings.
+/// The keyboard, mouse and gamepad are combined, so systems only ask what was meant.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    config: Res<'w, InputConfig>,
+    buttons: Res<'w, Input<MouseButton>>,
+    latch: Res<'w, PressLatch>,
+    scroll: EventReader<'w, 's, MouseWheel>,
+    gamepad: GamepadInput<'w, 's>,
+}
+
+impl ActionInput<'_, '_> {
+    fn pressed(&self, action: Action) -> bool {
+        self.config.pressed(&self.keys, action)
+    }
+
+    fn just_pressed(&self, action: Action) -> bool {
+        self.config.just_pressed(&self.keys, action)
+    }
+
+    fn latched(&self, action: Action) -> bool {
+        self.config.key(action).map_or(false, |key| self.latch.keys.contains(&key))
+    }
+
+    /// -1 for left and 1 for right, or in between for a light tilt of the stick.
+    pub fn move_axis(&self) -> f32 {
+        let keys = self.pressed(Action::Right) as i32 - self.pressed(Action::Left) as i32;
+        (keys as f32 + self.gamepad.axis(GamepadAxisType::LeftStickX)).clamp(-1., 1.)
+    }
+
+    /// -1 for down and 1 for up, for ladders.
+    pub fn climb_axis(&self) -> f32 {
+        let keys = self.pressed(Action::ClimbUp) as i32 - self.pressed(Action::ClimbDown) as i32;
+        (keys as f32 + self.gamepad.axis(GamepadAxisType::LeftStickY)).clamp(-1., 1.)
+    }
+
+    /// -1 for lowering the keyboard aim and 1 for raising it.
+    pub fn aim_axis(&self) -> f32 {
+        (self.pressed(Action::AimUp) as i32 - self.pressed(Action::AimDown) as i32) as f32
+    }
+
+    pub fn jump_pressed(&self) -> bool {
+        self.pressed(Action::Jump) || self.gamepad.pressed(GamepadButtonType::South)
+    }
+
+    pub fn jump_just_pressed(&self) -> bool {
+        self.latched(Action::Jump) || self.gamepad.just_pressed(GamepadButtonType::South)
+    }
+
+    pub fn crouch_pressed(&self) -> bool {
+        self.pressed(Action::Crouch) || self.gamepad.pressed(GamepadButtonType::East)
+    }
+
+    pub fn crouch_just_pressed(&self) -> bool {
+        self.just_pressed(Action::Crouch) || self.gamepad.just_pressed(GamepadButtonType::East)
+    }
+
+    pub fn dash_just_pressed(&self) -> bool {
+        self.just_pressed(Action::Dash) || self.gamepad.just_pressed(GamepadButtonType::North)
+    }
+
+    pub fn throw_pressed(&self) -> bool {
+        self.pressed(Action::Throw)
+            || self.buttons.pressed(MouseButton::Left)
+            || self.gamepad.pressed(GamepadButtonType::RightTrigger2)
+            || self.gamepad.pressed(GamepadButtonType::West)
+    }
+
+    pub fn throw_just_pressed(&self) -> bool {
+        self.latched(Action::Throw)
+            || self.latch.buttons.contains(&MouseButton::Left)
+            || self.gamepad.just_pressed(GamepadButtonType::RightTrigger2)
+            || self.gamepad.just_pressed(GamepadButtonType::West)
+    }
+
+    /// Which way to switch the active ability this frame, from the scroll wheel and the switch buttons.
+    /// Reads the scroll events, so it should only be called once a frame.
+    pub fn switch_delta(&mut self) -> i32 {
+        let mut delta: f32 = self.scroll.iter().map(|event| event.y).sum();
+
+        if self.just_pressed(Action::NextAbility) || self.gamepad.just_pressed(GamepadButtonType::RightTrigger) {
+            delta += 1.;
+        }
+        if self.just_pressed(Action::PrevAbility) || self.gamepad.just_pressed(GamepadButtonType::LeftTrigger) {
+            delta -= 1.;
+        }
+
+        if delta > 0. {
+            1
+        } else if delta < 0. {
+            -1
+        } else {
+            0
+        }
+    }
+}
+
+/// Every key and mouse button pressed this frame, read from the input events themselves.
+/// A tap that's pressed and released between two frames is still in here,
+/// so jumping and throwing don't hang on what the buttons looked like when the frame started.
+#[derive(Resource, Default)]
+pub struct PressLatch {
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<MouseButton>,
+}
+
+pub fn latch_presses(
+    mut latch: ResMut<PressLatch>,
+    mut keys: EventReader<KeyboardInput>,
+    mut buttons: EventReader<MouseButtonInput>,
+) {
+    latch.keys.clear();
+    latch.buttons.clear();
+
+    for event in keys.iter() {
+        if let (ButtonState::Pressed, Some(key)) = (event.state, event.key_code) {
+            latch.keys.insert(key);
+        }
+    }
+    for event in buttons.iter() {
+        if event.state == ButtonState::Pressed {
+            latch.buttons.insert(event.button);
+        }
+    }
+}
+
+/// A full set of bindings that can be switched to at once from the settings.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ControlPreset {
+    Default,
+    Southpaw,
+}
+
+impl ControlPreset {
+    pub fn bindings(&self) -> InputConfig {
+        match self {
+            Self::Default => InputConfig::default(),
+            Self::Southpaw => InputConfig::southpaw(),
+        }
+    }
+
+    /// The preset the bindings match, or nothing if keys have been rebound since.
+    pub fn matching(config: &InputConfig) -> Option<Self> {
+        Self::ALL.iter().copied().find(|preset| preset.bindings() == *config)
+    }
+}
+
+impl Choice for ControlPreset {
+    const ALL: &'static [Self] = &[Self::Default, Self::Southpaw];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Southpaw => "Southpaw",
+        }
+    }
+}
@@ -1,26 +1,42 @@
 #[cfg(feature = "native")]
-use std::time::Instant;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use bevy::{render::camera::Viewport, utils::Duration};
+use bevy::{asset::LoadState, input::InputSystem, render::camera::Viewport, utils::Duration};
 
 #[cfg(feature = "browser")]
 use stdweb::web::Date;
 
-use bevy::{prelude::*, time::Stopwatch};
-use bevy_ecs_ldtk::prelude::*;
+use bevy::{prelude::*, sprite::Anchor, time::Stopwatch};
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::*};
 use bevy_pixel_camera::PixelCameraBundle;
 use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
 
 use crate::{
-    animator::{AnimationIndices, AnimationTimer, DamageFlash},
-    enemies::EnemyDamageActivator,
-    world::{StandardFont, WorldCollider},
-    GameState, GameTimer,
+    animator::{
+        AnimationClip, AnimationStateMachine, BaseColor, DamageFlash, Lifetime, ParticleQuality,
+        SimpleMotion, YSorted,
+    },
+    enemies::{Enemy, EnemyDamageActivator, LevelScaling},
+    layers,
+    rng::GameRng,
+    score::{self, ScoreEvent, ScoreText},
+    unlocks::Cloak,
+    world::{
+        field_value, Checkpoint, Hazard, HazardDamage, HitStop, Ladder, LevelCount, StandardFont,
+        WinReason, WorldCollider,
+    },
+    GameMode, GameState, GameTimer, TimeBonus,
 };
 
-use self::abilities::DamageEffect;
+use self::{
+    abilities::{DamageEffect, HealthEffect},
+    controls::{latch_presses, ActionInput, InputConfig, PressLatch},
+    tutorial::PlayerSlammed,
+};
 
 pub mod abilities;
+pub mod controls;
+pub mod tutorial;
 
 pub struct PlayerPlugin;
 
@@ -30,21 +46,84 @@ impl Plugin for PlayerPlugin {
             .register_ldtk_entity::<PlayerBundle>("Player")
             .add_startup_system(spawn_camera)
             .insert_resource(PlayerHealth::default())
+            .insert_resource(MaxHealth::default())
+            .insert_resource(CameraZoom::default())
+            .insert_resource(LevelOverview(false))
+            .insert_resource(LevelBounds::default())
+            .insert_resource(CameraConfig::default())
+            .insert_resource(Transitioning::default())
+            .insert_resource(Brightness::default())
+            .insert_resource(ScreenShake::default())
+            .insert_resource(ScreenShakeEnabled(true))
+            .insert_resource(ShowHud(true))
+            .insert_resource(HudLayout::default())
+            .insert_resource(InputConfig::default())
+            .insert_resource(PressLatch::default())
+            // Straight after the input is read, so every system this frame sees the same presses
+            .add_system(latch_presses.in_base_set(CoreSet::PreUpdate).after(InputSystem))
+            .insert_resource(DoubleJumpEnabled(false))
+            .insert_resource(PlayerInput::default())
+            .insert_resource(PlayerSkin::default())
+            .insert_resource(FixedTime::new_from_secs(MOVEMENT_STEP))
+            .add_system(player_movement.in_schedule(CoreSchedule::FixedUpdate))
+            .insert_resource(SpawnProtection(Duration::from_millis(1500)))
             .add_systems((
                 on_player_spawn,
                 player_physics_checks,
-                player_movement.after(player_physics_checks),
+                read_player_input,
+                update_player_animation,
                 camera_controller,
+                update_level_bounds.before(camera_controller),
+                reset_screen_shake.before(camera_controller),
+                screen_shake.after(camera_controller).after(level_overview),
+                add_damage_shake.before(screen_shake),
                 update_viewport,
-                update_player_health_ui,
+                toggle_level_overview,
+                level_overview.after(update_viewport),
+                update_brightness,
+            ))
+            .add_systems((
+                blink_invulnerable,
+                player_health_effects,
+                expire_buffs,
+                spawn_buff_aura,
+                update_buff_aura,
                 game_over,
-                switch_levels,
+                hazard_damage,
+                ladder_checks,
+                update_player_skin,
+                // Before the score is added up, so the win screen counts the time left
+                switch_levels.before(score::accumulate_score),
                 update_timer,
+                apply_time_bonus,
+            ))
+            .add_systems((
+                update_status_hud,
+                update_player_health_ui,
+                update_heart_count,
+                update_floating_text,
                 spawn_player_ui,
                 despawn_player_ui,
+                update_hud_visibility,
+            ));
+
+        app.add_event::<PlayerDamaged>()
+            .add_event::<SlamLanded>()
+            .insert_resource(DamageTaken(false))
+            .add_system(record_damage_taken)
+            .insert_resource(SlamAttack::default())
+            .insert_resource(SlamCombo::default())
+            .add_system(slam_impact.before(screen_shake))
+            .add_system(slam_attack)
+            .add_system(update_combo_ui.after(slam_attack))
+            .add_systems((
+                spawn_damage_indicators,
+                update_damage_indicators,
+                update_enemy_indicators,
             ));
 
-        app.add_plugin(abilities::AbilityPlugin);
+        app.add_plugin(abilities::AbilityPlugin)
+            .add_plugin(tutorial::TutorialPlugin);
 
         let asset_server = app.world.resource::<AssetServer>();
         app.insert_resource(HeartImages {
@@ -54,6 +133,20 @@ impl Plugin for PlayerPlugin {
             full_flash: asset_server.load("images/heart/full_flash.png"),
             half_flash: asset_server.load("images/heart/half_flash.png"),
         });
+
+        let mesh = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::RegularPolygon::new(8., 3).into());
+        let mut materials = app.world.resource_mut::<Assets<ColorMaterial>>();
+        let damage_material = materials.add(ColorMaterial::from(Color::rgba(1.0, 0.2, 0.2, 0.8)));
+        let enemy_material = materials.add(ColorMaterial::from(Color::rgba(1.0, 1.0, 1.0, 0.5)));
+
+        app.insert_resource(IndicatorAssets {
+            mesh,
+            damage_material,
+            enemy_material,
+        });
     }
 }
 
@@ -66,6 +159,21 @@ impl Default for PlayerHealth {
     }
 }
 
+/// Whether the player has been hurt this run. Kept apart from [`PlayerHealth`],
+/// since respawning at a checkpoint heals them back to full.
+#[derive(Resource, Default)]
+pub struct DamageTaken(pub bool);
+
+/// The most health the player can have. Every 2 points is one heart on the HUD.
+#[derive(Resource)]
+pub struct MaxHealth(pub i32);
+
+impl Default for MaxHealth {
+    fn default() -> Self {
+        Self(6)
+    }
+}
+
 #[derive(Component)]
 pub struct Player;
 
@@ -74,14 +182,40 @@ pub struct PlayerPhysics {
     pub total_ground_collisions: i32,
     pub grounded: bool,
     pub slamming: bool,
-    #[cfg(feature = "native")]
-    pub early_jump: Option<Instant>,
-    #[cfg(feature = "browser")]
+    /// Jumps left before landing again, including the one from the ground.
+    pub jumps_remaining: u32,
+    pub left_wall_collisions: i32,
+    pub right_wall_collisions: i32,
+    /// The x of the normal of the wall the player is pressed against, pointing away from it.
+    pub touching_wall: Option<f32>,
+    /// Seconds left where horizontal input is ignored after a wall jump.
+    pub wall_jump_lock: f32,
+    /// Seconds left in the current dash, during which gravity is ignored.
+    pub dash_time: f32,
+    /// Seconds before the player can dash again.
+    pub dash_cooldown: f32,
+    /// Whether the player has used their dash since they last touched the ground.
+    pub dashed_in_air: bool,
+    /// When jump was pressed in the air, in [`now_millis`] time.
     pub early_jump: Option<f64>,
-    #[cfg(feature = "native")]
-    pub coyote_time: Option<Instant>,
-    #[cfg(feature = "browser")]
+    /// When the player was last on the ground, in [`now_millis`] time.
     pub coyote_time: Option<f64>,
+    /// Whether the player is inside a [`Ladder`], and could start climbing it.
+    pub on_ladder: bool,
+    /// Whether the player is holding onto a ladder, which replaces gravity and running with climbing.
+    pub climbing: bool,
+}
+
+impl PlayerPhysics {
+    fn update_touching_wall(&mut self) {
+        self.touching_wall = if self.left_wall_collisions > 0 {
+            Some(1.)
+        } else if self.right_wall_collisions > 0 {
+            Some(-1.)
+        } else {
+            None
+        };
+    }
 }
 
 #[derive(Bundle)]
@@ -95,8 +229,7 @@ pub struct PlayerBundle {
     pub collider: Collider,
     pub collision_groups: CollisionGroups,
     pub locked_axes: LockedAxes,
-    pub animation_indices: AnimationIndices,
-    pub animation_timer: AnimationTimer,
+    pub animation: AnimationStateMachine,
     pub sprite: TextureAtlasSprite,
     pub texture_atlas: Handle<TextureAtlas>,
 }
@@ -110,9 +243,8 @@ impl LdtkEntity for PlayerBundle {
         asset_server: &AssetServer,
         texture_atlases: &mut Assets<TextureAtlas>,
     ) -> Self {
-        let texture = asset_server.load("images/cloak_spritesheet.png");
-        let texture_atlas = TextureAtlas::from_grid(texture, Vec2::new(32., 32.), 2, 2, None, None);
-        let texture_atlas = texture_atlases.add(texture_atlas);
+        // Entities are built without access to resources, so a chosen skin is swapped in by `update_player_skin`
+        let texture_atlas = player_atlas(asset_server.load(DEFAULT_PLAYER_SKIN), texture_atlases);
 
         Self {
             player: Player,
@@ -125,10 +257,18 @@ impl LdtkEntity for PlayerBundle {
             },
             mass: ColliderMassProperties::Density(2.0),
             collider: Collider::capsule_y(5., 11.),
-            collision_groups: CollisionGroups::new(Group::GROUP_2, Group::GROUP_1 | Group::GROUP_4 | Group::GROUP_6),
+            // Potions only include the player in their filters with `ChaosPotions`
+            collision_groups: CollisionGroups::new(
+                Group::GROUP_2,
+                Group::GROUP_1
+                    | Group::GROUP_4
+                    | Group::GROUP_5
+                    | Group::GROUP_6
+                    | Group::GROUP_8
+                    | Group::GROUP_9,
+            ),
             locked_axes: LockedAxes::ROTATION_LOCKED,
-            animation_indices: AnimationIndices { first: 0, last: 3 },
-            animation_timer: AnimationTimer(Timer::from_seconds(1. / 12., TimerMode::Repeating)),
+            animation: PlayerAnimState::animations(),
             sprite: TextureAtlasSprite::default(),
             texture_atlas,
         }
@@ -141,8 +281,425 @@ pub struct PlayerGroundSensor;
 #[derive(Component)]
 pub struct PlayerDamageSensor;
 
-fn on_player_spawn(mut commands: Commands, player: Query<Entity, Added<Player>>) {
-    let Ok(player) = player.get_single() else { return };
+#[derive(Component)]
+pub struct PlayerSensorLeft;
+
+#[derive(Component)]
+pub struct PlayerSensorRight;
+
+/// Enemy contact deals no damage while this is on the player.
+#[derive(Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+}
+
+impl Invulnerable {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            timer: Timer::new(duration, TimerMode::Once),
+        }
+    }
+}
+
+/// What a [`PlayerBuff`] boosts, and by how much.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BuffKind {
+    Speed(f32),
+    Jump(f32),
+}
+
+impl BuffKind {
+    pub fn speed(&self) -> f32 {
+        match self {
+            Self::Speed(multiplier) => *multiplier,
+            Self::Jump(_) => 1.0,
+        }
+    }
+
+    pub fn jump(&self) -> f32 {
+        match self {
+            Self::Speed(_) => 1.0,
+            Self::Jump(multiplier) => *multiplier,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Speed(_) => Color::rgb(0.4, 1.0, 0.4),
+            Self::Jump(_) => Color::rgb(1.0, 0.9, 0.3),
+        }
+    }
+}
+
+/// Boosts the player's movement until the timer runs out.
+#[derive(Component)]
+pub struct PlayerBuff {
+    pub kind: BuffKind,
+    pub timer: Timer,
+}
+
+fn expire_buffs(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut PlayerBuff)>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+) {
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    for (entity, mut buff) in player.iter_mut() {
+        buff.timer.tick(time.delta());
+        if buff.timer.finished() {
+            commands.entity(entity).remove::<PlayerBuff>();
+        }
+    }
+}
+
+const STATUS_SLOTS: usize = 4;
+const STATUS_BAR_WIDTH: f32 = 24.;
+
+/// Shows one of the player's active status effects on the HUD, by its place in the list.
+#[derive(Component)]
+struct StatusSlot(usize);
+
+/// Shrinks as the status effect in its slot runs out.
+#[derive(Component)]
+struct StatusBar;
+
+fn update_status_hud(
+    mut slots: Query<(&StatusSlot, &mut Handle<Image>, &mut Visibility, &Children)>,
+    mut bars: Query<&mut Transform, With<StatusBar>>,
+    player: Query<(Option<&PlayerBuff>, Option<&Invulnerable>), With<Player>>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut statuses = Vec::with_capacity(STATUS_SLOTS);
+
+    if let Ok((buff, invulnerable)) = player.get_single() {
+        if let Some(buff) = buff {
+            let image = match buff.kind {
+                BuffKind::Speed(_) => asset_server.load("images/abilities/green.png"),
+                BuffKind::Jump(_) => asset_server.load("images/abilities/yellow.png"),
+            };
+            statuses.push((image, buff.timer.percent_left()));
+        }
+
+        if let Some(invulnerable) = invulnerable {
+            statuses.push((asset_server.load("images/heart/gold.png"), invulnerable.timer.percent_left()));
+        }
+    }
+
+    for (slot, mut image, mut visibility, children) in slots.iter_mut() {
+        let Some((status_image, left)) = statuses.get(slot.0) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *image = status_image.clone();
+        *visibility = Visibility::Inherited;
+
+        for child in children.iter() {
+            if let Ok(mut transform) = bars.get_mut(*child) {
+                transform.scale.x = *left;
+            }
+        }
+    }
+}
+
+/// A particle drifting up from a buffed player.
+#[derive(Component)]
+struct AuraParticle;
+
+#[derive(Component)]
+struct AuraTimer(Timer);
+
+fn spawn_buff_aura(
+    mut commands: Commands,
+    mut player: Query<(Entity, &Transform, &PlayerBuff, Option<&mut AuraTimer>)>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    quality: Res<ParticleQuality>,
+) {
+    let Ok((entity, transform, buff, timer)) = player.get_single_mut() else { return };
+
+    let Some(mut timer) = timer else {
+        commands
+            .entity(entity)
+            .insert(AuraTimer(Timer::from_seconds(0.16, TimerMode::Repeating)));
+        return;
+    };
+
+    timer.0.tick(time.delta());
+    if !timer.0.just_finished() {
+        return;
+    }
+
+    for _ in 0..quality.scale(2) {
+        let offset = Vec2::new((rng.next_f32() - 0.5) * 20., (rng.next_f32() - 0.5) * 24.);
+
+        commands.spawn((
+            AuraParticle,
+            Lifetime::from_seconds(0.5),
+            SimpleMotion {
+                velocity: Vec2::new(0., 24.),
+                ..default()
+            },
+            SpriteBundle {
+                sprite: Sprite {
+                    color: buff.kind.color(),
+                    custom_size: Some(Vec2::splat(2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(
+                    (transform.translation.truncate() + offset).extend(layers::EFFECTS),
+                ),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_buff_aura(mut particles: Query<(&Lifetime, &mut Sprite), With<AuraParticle>>) {
+    for (lifetime, mut sprite) in particles.iter_mut() {
+        sprite.color.set_a(lifetime.0.percent_left());
+    }
+}
+
+fn player_health_effects(
+    mut commands: Commands,
+    player: Query<(Entity, &HealthEffect), With<Player>>,
+    mut health: ResMut<PlayerHealth>,
+    mut damage_taken: ResMut<DamageTaken>,
+) {
+    for (entity, effect) in player.iter() {
+        health.0 += effect.amount;
+        // The player's own potions hurt them without knocking them about, so there's no PlayerDamaged
+        if effect.amount < 0 {
+            damage_taken.0 = true;
+        }
+        commands.entity(entity).remove::<HealthEffect>();
+    }
+}
+
+/// How long the player is invulnerable after entering a level.
+#[derive(Resource)]
+pub struct SpawnProtection(pub Duration);
+
+fn blink_invulnerable(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut visibility) in player.iter_mut() {
+        invulnerable.timer.tick(time.delta());
+
+        if invulnerable.timer.finished() {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<Invulnerable>();
+        } else if (invulnerable.timer.elapsed_secs() / 0.1) as u32 % 2 == 0 {
+            *visibility = Visibility::Hidden;
+        } else {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+/// The player spritesheet has a row for each animation.
+const PLAYER_SHEET_COLUMNS: usize = 4;
+const PLAYER_SHEET_ROWS: usize = 4;
+const PLAYER_FRAME_SIZE: Vec2 = Vec2::new(32., 32.);
+
+const DEFAULT_PLAYER_SKIN: &str = "images/cloak_spritesheet.png";
+
+fn player_atlas(texture: Handle<Image>, texture_atlases: &mut Assets<TextureAtlas>) -> Handle<TextureAtlas> {
+    texture_atlases.add(TextureAtlas::from_grid(
+        texture,
+        PLAYER_FRAME_SIZE,
+        PLAYER_SHEET_COLUMNS,
+        PLAYER_SHEET_ROWS,
+        None,
+        None,
+    ))
+}
+
+/// Which spritesheet the player is drawn with, for swapping in different character art.
+/// A sheet has to be laid out the same as the default one, or the default is used instead.
+#[derive(Resource)]
+pub struct PlayerSkin {
+    /// The sheet's path in the assets folder.
+    pub path: String,
+    /// The sheet loaded from `path`, and which path that was, so changing it loads the new one.
+    loaded: Option<(String, Handle<Image>)>,
+    /// The atlas for the loaded sheet, once it's been checked.
+    atlas: Option<Handle<TextureAtlas>>,
+}
+
+impl Default for PlayerSkin {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_PLAYER_SKIN.to_owned(),
+            loaded: None,
+            atlas: None,
+        }
+    }
+}
+
+impl PlayerSkin {
+    fn use_default(&mut self) {
+        self.path = DEFAULT_PLAYER_SKIN.to_owned();
+    }
+}
+
+/// Loads and checks the chosen [`PlayerSkin`], then puts it on the player, including any that spawn later.
+fn update_player_skin(
+    mut skin: ResMut<PlayerSkin>,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut player: Query<&mut Handle<TextureAtlas>, With<Player>>,
+    spawned: Query<(), Added<Player>>,
+) {
+    if skin.loaded.as_ref().map_or(true, |(path, _)| *path != skin.path) {
+        let texture = asset_server.load(skin.path.as_str());
+        skin.loaded = Some((skin.path.clone(), texture));
+        skin.atlas = None;
+    }
+
+    // Players that spawn later start with the default, so they get the skin put back on
+    if let Some(atlas) = skin.atlas.clone() {
+        if !spawned.is_empty() {
+            for mut handle in player.iter_mut() {
+                *handle = atlas.clone();
+            }
+        }
+        return;
+    }
+
+    let Some((path, texture)) = skin.loaded.clone() else { return };
+
+    match asset_server.get_load_state(&texture) {
+        LoadState::Loaded => {}
+        LoadState::Failed => {
+            warn!("Failed to load the player skin {path}, using the default instead");
+            skin.use_default();
+            return;
+        }
+        _ => return,
+    }
+
+    let Some(image) = images.get(&texture) else { return };
+
+    // Frames are cut out on a fixed grid, so any other size would slice the animations wrong
+    let expected = PLAYER_FRAME_SIZE * Vec2::new(PLAYER_SHEET_COLUMNS as f32, PLAYER_SHEET_ROWS as f32);
+    if image.size() != expected {
+        warn!(
+            "The player skin {path} is {}x{}, but it needs to be {}x{}, using the default instead",
+            image.size().x,
+            image.size().y,
+            expected.x,
+            expected.y,
+        );
+        skin.use_default();
+        return;
+    }
+
+    let atlas = player_atlas(texture, &mut texture_atlases);
+    skin.atlas = Some(atlas.clone());
+
+    for mut handle in player.iter_mut() {
+        *handle = atlas.clone();
+    }
+}
+
+/// The first and last frames of each of the player's animations.
+const PLAYER_IDLE_FRAMES: (usize, usize) = (0, 3);
+const PLAYER_RUN_FRAMES: (usize, usize) = (4, 7);
+const PLAYER_JUMP_FRAMES: (usize, usize) = (8, 11);
+const PLAYER_SLAM_FRAMES: (usize, usize) = (12, 15);
+
+/// How fast the player has to move sideways to count as running.
+const RUN_THRESHOLD: f32 = 10.;
+
+/// What the player is doing, which picks their animation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlayerAnimState {
+    Idle,
+    Running,
+    Jumping,
+    Slamming,
+}
+
+impl PlayerAnimState {
+    const ALL: [Self; 4] = [Self::Idle, Self::Running, Self::Jumping, Self::Slamming];
+
+    /// The name of the state's clip in the player's [`AnimationStateMachine`].
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Running => "run",
+            Self::Jumping => "jump",
+            Self::Slamming => "slam",
+        }
+    }
+
+    fn clip(&self) -> AnimationClip {
+        let (first, last) = match self {
+            Self::Idle => PLAYER_IDLE_FRAMES,
+            Self::Running => PLAYER_RUN_FRAMES,
+            Self::Jumping => PLAYER_JUMP_FRAMES,
+            Self::Slamming => PLAYER_SLAM_FRAMES,
+        };
+        AnimationClip::new(first, last, 12.)
+    }
+
+    fn animations() -> AnimationStateMachine {
+        Self::ALL.into_iter().fold(
+            AnimationStateMachine::new(Self::Idle.name(), Self::Idle.clip()),
+            |machine, state| machine.with_clip(state.name(), state.clip()),
+        )
+    }
+}
+
+fn update_player_animation(
+    mut player: Query<(&PlayerPhysics, &Velocity, &mut AnimationStateMachine), Without<Dying>>,
+) {
+    for (physics, velocity, mut animation) in player.iter_mut() {
+        let state = if physics.slamming {
+            PlayerAnimState::Slamming
+        } else if !physics.grounded {
+            PlayerAnimState::Jumping
+        } else if velocity.linvel.x.abs() > RUN_THRESHOLD {
+            PlayerAnimState::Running
+        } else {
+            PlayerAnimState::Idle
+        };
+
+        animation.set_state(state.name());
+    }
+}
+
+fn on_player_spawn(
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut Transform, &mut TextureAtlasSprite), Added<Player>>,
+    protection: Res<SpawnProtection>,
+    cloak: Res<Cloak>,
+    mut checkpoint: ResMut<Checkpoint>,
+) {
+    let Ok((player, mut transform, mut sprite)) = player.get_single_mut() else { return };
+    if std::mem::take(&mut checkpoint.respawning) {
+        if let Some((_, position)) = checkpoint.last {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+    }
+    transform.translation.z = layers::PLAYER;
+    sprite.color = cloak.color();
+    commands
+        .entity(player)
+        .insert((YSorted { band: layers::PLAYER }, BaseColor(cloak.color())));
+    if !protection.0.is_zero() {
+        commands.entity(player).insert(Invulnerable::new(protection.0));
+    }
     commands.entity(player).with_children(|parent| {
         parent.spawn((
             PlayerGroundSensor,
@@ -150,7 +707,7 @@ fn on_player_spawn(mut commands: Commands, player: Query<Entity, Added<Player>>)
             Collider::cuboid(8., 8.),
             ActiveEvents::COLLISION_EVENTS,
             ActiveHooks::MODIFY_SOLVER_CONTACTS,
-            CollisionGroups::new(Group::GROUP_3, Group::GROUP_1 | Group::GROUP_4),
+            CollisionGroups::new(Group::GROUP_3, Group::GROUP_1 | Group::GROUP_4 | Group::GROUP_8),
             TransformBundle {
                 local: Transform::from_xyz(0., -11., 0.),
                 ..default()
@@ -162,15 +719,60 @@ fn on_player_spawn(mut commands: Commands, player: Query<Entity, Added<Player>>)
             Sensor,
             Collider::capsule_y(5., 12.),
             ActiveEvents::COLLISION_EVENTS,
-            CollisionGroups::new(Group::GROUP_5, Group::GROUP_5),
+            // Enemies and pickups, hazards, and arrows
+            CollisionGroups::new(Group::GROUP_5, Group::GROUP_5 | Group::GROUP_7 | Group::GROUP_10),
             TransformBundle::default(),
         ));
+
+        parent.spawn((
+            PlayerSensorLeft,
+            Sensor,
+            Collider::cuboid(2., 6.),
+            ActiveEvents::COLLISION_EVENTS,
+            CollisionGroups::new(Group::GROUP_3, Group::GROUP_1),
+            TransformBundle {
+                local: Transform::from_xyz(-12., 2., 0.),
+                ..default()
+            },
+        ));
+
+        parent.spawn((
+            PlayerSensorRight,
+            Sensor,
+            Collider::cuboid(2., 6.),
+            ActiveEvents::COLLISION_EVENTS,
+            CollisionGroups::new(Group::GROUP_3, Group::GROUP_1),
+            TransformBundle {
+                local: Transform::from_xyz(12., 2., 0.),
+                ..default()
+            },
+        ));
     });
 }
 
 #[derive(Component)]
 pub struct MainCamera;
 
+/// How much of the world is visible, relative to the 480x320 design resolution.
+#[derive(Resource, Clone, Copy, PartialEq)]
+pub struct CameraZoom(pub f32);
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl CameraZoom {
+    pub const MIN: f32 = 1.0;
+    pub const MAX: f32 = 2.0;
+    pub const STEP: f32 = 0.5;
+
+    pub fn step(&mut self, delta: i32) {
+        self.0 = (self.0 + delta as f32 * Self::STEP).clamp(Self::MIN, Self::MAX);
+    }
+}
+
 #[derive(Resource)]
 struct HeartImages {
     full: Handle<Image>,
@@ -181,95 +783,690 @@ struct HeartImages {
 }
 
 #[derive(Component)]
-struct Heart<const ID: u8>;
+struct HeartSlot {
+    index: usize,
+}
+
+impl HeartSlot {
+    /// How much of this heart is filled: 0 for empty, 1 for half, 2 for full.
+    fn fill(&self, hp: i32) -> i32 {
+        // Each heart holds 2 health, filling from the left
+        (hp - 2 * self.index as i32).clamp(0, 2)
+    }
+}
 
 #[derive(Component)]
 struct GameTimerUi;
 
-fn spawn_camera(mut commands: Commands) {
-    commands.spawn((
-        MainCamera,
-        PixelCameraBundle::from_resolution(480, 320),
-        VisibilityBundle::default(),
-    ));
+fn spawn_camera(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands
+        .spawn((
+            MainCamera,
+            PixelCameraBundle::from_resolution(480, 320),
+            VisibilityBundle::default(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                BrightnessOverlay,
+                ColorMesh2dBundle {
+                    mesh: meshes
+                        .add(shape::Quad::new(Vec2::new(480., 320.)).into())
+                        .into(),
+                    material: materials.add(ColorMaterial::from(Color::NONE)),
+                    transform: Transform::from_xyz(0., 0., layers::SCREEN_FILTER),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Brightens or darkens the whole screen. 1.0 leaves it unchanged.
+#[derive(Resource, Clone, Copy, PartialEq)]
+pub struct Brightness(pub f32);
+
+impl Default for Brightness {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl Brightness {
+    pub const MIN: f32 = 0.5;
+    pub const MAX: f32 = 1.5;
+    pub const STEP: f32 = 0.1;
+
+    pub fn step(&mut self, delta: i32) {
+        self.0 = (self.0 + delta as f32 * Self::STEP).clamp(Self::MIN, Self::MAX);
+    }
+}
+
+/// A fullscreen quad over everything the camera sees, tinted to adjust the brightness.
+#[derive(Component)]
+struct BrightnessOverlay;
+
+fn update_brightness(
+    brightness: Res<Brightness>,
+    mut overlay: Query<(&Handle<ColorMaterial>, &mut Visibility), With<BrightnessOverlay>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !brightness.is_changed() {
+        return;
+    }
+
+    let Ok((material, mut visibility)) = overlay.get_single_mut() else { return };
+    let Some(material) = materials.get_mut(material) else { return };
+
+    let offset = brightness.0 - 1.0;
+
+    if offset.abs() < 0.01 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    *visibility = Visibility::Inherited;
+    material.color = if offset > 0.0 {
+        Color::rgba(1.0, 1.0, 1.0, offset * 0.5)
+    } else {
+        Color::rgba(0.0, 0.0, 0.0, -offset)
+    };
 }
 
 #[derive(Component)]
 struct PlayerUi;
 
+/// Whether the HUD is drawn during gameplay. Hiding it leaves the game fully playable.
+#[derive(Resource)]
+pub struct ShowHud(pub bool);
+
+/// A piece of the HUD that's hidden along with the rest of it by [`ShowHud`].
+#[derive(Component)]
+pub struct HudElement;
+
+impl ShowHud {
+    pub fn visibility(&self) -> Visibility {
+        if self.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    }
+}
+
+/// How the HUD is arranged. The compact layout squeezes hearts, the timer and abilities into one
+/// bar along the bottom of the screen, for small screens.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HudLayout {
+    #[default]
+    Full,
+    Compact,
+}
+
+impl HudLayout {
+    /// Where the compact layout's bar sits.
+    pub const COMPACT_BAR_Y: f32 = -152.;
+
+    pub fn toggle(&mut self) {
+        *self = match self {
+            Self::Full => Self::Compact,
+            Self::Compact => Self::Full,
+        };
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Full => "Full",
+            Self::Compact => "Compact",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Full" => Some(Self::Full),
+            "Compact" => Some(Self::Compact),
+            _ => None,
+        }
+    }
+}
+
+/// Also catches elements that spawn partway through a run, like cooldown overlays.
+fn update_hud_visibility(
+    show_hud: Res<ShowHud>,
+    mut ui: Query<&mut Visibility, With<HudElement>>,
+    spawned: Query<Entity, Added<HudElement>>,
+) {
+    if show_hud.is_changed() {
+        for mut visibility in ui.iter_mut() {
+            *visibility = show_hud.visibility();
+        }
+        return;
+    }
+
+    for entity in spawned.iter() {
+        if let Ok(mut visibility) = ui.get_mut(entity) {
+            *visibility = show_hud.visibility();
+        }
+    }
+}
+
 fn spawn_player_ui(
     mut commands: Commands,
     camera: Query<Entity, With<MainCamera>>,
     game_state: Res<GameState>,
     heart_images: Res<HeartImages>,
+    max_health: Res<MaxHealth>,
+    ui: Query<(), With<PlayerUi>>,
+    show_hud: Res<ShowHud>,
+    layout: Res<HudLayout>,
 ) {
-    if game_state.is_changed() && *game_state == GameState::Gameplay {
+    if game_state.is_changed() && *game_state == GameState::Gameplay && ui.is_empty() {
         let Ok(camera) = camera.get_single() else { return };
+        let (status_y, timer_position, score_position) = match *layout {
+            HudLayout::Full => (-96., Vec2::new(0., 150.), Vec2::new(176., 150.)),
+            // Statuses sit just above the bar, so they don't cover the hearts
+            HudLayout::Compact => (
+                HudLayout::COMPACT_BAR_Y + 28.,
+                Vec2::new(0., HudLayout::COMPACT_BAR_Y),
+                Vec2::new(72., HudLayout::COMPACT_BAR_Y),
+            ),
+        };
+
         commands.entity(camera).with_children(|parent| {
             parent
-                .spawn(PlayerUi)
-                .insert(SpatialBundle::default())
+                .spawn((PlayerUi, HudElement))
+                .insert(SpatialBundle {
+                    visibility: show_hud.visibility(),
+                    ..default()
+                })
                 .with_children(|parent| {
-                    parent
-                        .spawn(SpriteBundle {
-                            texture: heart_images.full.clone(),
-                            transform: Transform::from_xyz(-208., -128., -1.),
-                            ..default()
-                        })
-                        .insert(Heart::<0>);
-
-                    parent
-                        .spawn(SpriteBundle {
-                            texture: heart_images.full.clone(),
-                            transform: Transform::from_xyz(-172., -128., -1.),
+                    if *layout == HudLayout::Compact {
+                        parent.spawn(SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgba(0., 0., 0., 0.5),
+                                custom_size: Some(Vec2::new(512., 24.)),
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(0., HudLayout::COMPACT_BAR_Y, layers::HUD_BACKGROUND),
                             ..default()
-                        })
-                        .insert(Heart::<1>);
+                        });
+                    }
 
-                    parent
-                        .spawn(SpriteBundle {
-                            texture: heart_images.full.clone(),
-                            transform: Transform::from_xyz(-136., -128., -1.),
-                            ..default()
-                        })
-                        .insert(Heart::<2>);
+                    spawn_hearts(parent, &max_health, &heart_images, *layout);
+
+                    for i in 0..STATUS_SLOTS {
+                        parent
+                            .spawn((
+                                StatusSlot(i),
+                                SpriteBundle {
+                                    transform: Transform::from_xyz(-208. + 32. * i as f32, status_y, layers::HUD),
+                                    visibility: Visibility::Hidden,
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    StatusBar,
+                                    SpriteBundle {
+                                        sprite: Sprite {
+                                            color: Color::WHITE,
+                                            custom_size: Some(Vec2::new(STATUS_BAR_WIDTH, 2.)),
+                                            // Shrinks towards the left as it scales
+                                            anchor: Anchor::CenterLeft,
+                                            ..default()
+                                        },
+                                        transform: Transform::from_xyz(-STATUS_BAR_WIDTH / 2., -18., 0.1),
+                                        ..default()
+                                    },
+                                ));
+                            });
+                    }
 
                     parent
                         .spawn(Text2dBundle {
-                            transform: Transform::from_xyz(0., 150., -1.),
+                            transform: Transform::from_translation(timer_position.extend(layers::HUD)),
                             ..default()
                         })
                         .insert(GameTimerUi);
+
+                    parent.spawn((
+                        ScoreText,
+                        Text2dBundle {
+                            transform: Transform::from_translation(score_position.extend(layers::HUD)),
+                            ..default()
+                        },
+                    ));
+
+                    parent.spawn((
+                        ComboText,
+                        Text2dBundle {
+                            transform: Transform::from_xyz(0., 124., layers::HUD),
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                    ));
                 });
         });
     }
 }
 
+fn spawn_hearts(parent: &mut ChildBuilder, max_health: &MaxHealth, heart_images: &HeartImages, layout: HudLayout) {
+    let count = (max_health.0.max(0) as usize + 1) / 2;
+    for i in 0..count {
+        let transform = match layout {
+            HudLayout::Full => Transform::from_xyz(-208. + 36. * i as f32, -128., layers::HUD),
+            HudLayout::Compact => Transform::from_xyz(-240. + 18. * i as f32, HudLayout::COMPACT_BAR_Y, layers::HUD)
+                .with_scale(Vec3::splat(0.5)),
+        };
+
+        parent
+            .spawn(SpriteBundle {
+                texture: heart_images.full.clone(),
+                transform,
+                ..default()
+            })
+            .insert(HeartSlot { index: i });
+    }
+}
+
+fn update_heart_count(
+    mut commands: Commands,
+    max_health: Res<MaxHealth>,
+    ui: Query<Entity, With<PlayerUi>>,
+    hearts: Query<Entity, With<HeartSlot>>,
+    heart_images: Res<HeartImages>,
+    layout: Res<HudLayout>,
+) {
+    if !max_health.is_changed() {
+        return;
+    }
+    let Ok(ui) = ui.get_single() else { return };
+
+    for heart in hearts.iter() {
+        commands.entity(heart).despawn();
+    }
+
+    commands.entity(ui).with_children(|parent| {
+        spawn_hearts(parent, &max_health, &heart_images, *layout);
+    });
+}
+
 fn despawn_player_ui(
     mut commands: Commands,
     ui: Query<Entity, With<PlayerUi>>,
     game_state: Res<GameState>,
 ) {
-    if game_state.is_changed() && *game_state != GameState::Gameplay {
+    if game_state.is_changed() && !game_state.in_run() {
         let Ok(ui) = ui.get_single() else { return };
         commands.entity(ui).despawn_recursive();
     }
 }
 
+/// The loaded level's rectangle in the world, for keeping the camera inside it.
+#[derive(Resource, Default)]
+pub struct LevelBounds(pub Option<Rect>);
+
+fn update_level_bounds(
+    mut bounds: ResMut<LevelBounds>,
+    level: Query<(&Handle<LdtkLevel>, &GlobalTransform)>,
+    levels: Res<Assets<LdtkLevel>>,
+) {
+    let rect = level.get_single().ok().and_then(|(handle, transform)| {
+        let level = levels.get(handle)?;
+        let min = transform.translation().truncate();
+        let size = Vec2::new(level.level.px_wid as f32, level.level.px_hei as f32);
+        Some(Rect::from_corners(min, min + size))
+    });
+
+    if bounds.0 != rect {
+        bounds.0 = rect;
+    }
+}
+
+/// The size of the world the camera shows at a zoom of 1.
+const VIEW_SIZE: Vec2 = Vec2::new(480., 320.);
+
+/// Moves a view of `size` centered on `target` so it stays inside `bounds`.
+/// On an axis where the level is smaller than the view, the level is centered instead.
+fn clamp_view(target: Vec2, size: Vec2, bounds: Rect) -> Vec2 {
+    let axis = |target: f32, size: f32, min: f32, max: f32| {
+        if max - min < size {
+            (min + max) / 2.
+        } else {
+            target.clamp(min + size / 2., max - size / 2.)
+        }
+    };
+
+    Vec2::new(
+        axis(target.x, size.x, bounds.min.x, bounds.max.x),
+        axis(target.y, size.y, bounds.min.y, bounds.max.y),
+    )
+}
+
+/// How the camera follows the player.
+#[derive(Resource)]
+pub struct CameraConfig {
+    /// How quickly the camera catches up with the player, per second. Higher is snappier.
+    pub smoothing: f32,
+    /// How far, in pixels, the player can move from where the camera is looking before it follows.
+    pub deadzone: Vec2,
+    /// How far above the player the camera looks.
+    pub y_offset: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            smoothing: 8.,
+            deadzone: Vec2::new(16., 8.),
+            y_offset: 75.,
+        }
+    }
+}
+
 fn camera_controller(
     player_transform: Query<&Transform, With<Player>>,
+    spawned: Query<(), Added<Player>>,
     mut camera_transform: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
+    overview: Res<LevelOverview>,
+    bounds: Res<LevelBounds>,
+    config: Res<CameraConfig>,
+    time: Res<Time>,
+) {
+    if overview.0 {
+        return;
+    }
+
+    let Ok(mut camera_transform) = camera_transform.get_single_mut() else { return };
+    let Ok(player_transform) = player_transform.get_single() else { return };
+
+    let desired = player_transform.translation.truncate() + Vec2::Y * config.y_offset;
+    let current = camera_transform.translation.truncate();
+
+    let position = if spawned.is_empty() {
+        // Only follow once the player leaves the dead zone, keeping them at its edge
+        let offset = desired - current;
+        let excess = offset.signum() * (offset.abs() - config.deadzone).max(Vec2::ZERO);
+
+        // Exponential smoothing, so it catches up at the same rate whatever the frame rate
+        let t = 1. - (-config.smoothing * time.delta_seconds()).exp();
+        current + excess * t
+    } else {
+        // Snap to a player that just spawned, instead of sweeping over from the last level
+        desired
+    };
+
+    // Zooming out scales the camera, which shows more of the level
+    let size = VIEW_SIZE * camera_transform.scale.truncate();
+    let position = bounds.0.map_or(position, |bounds| clamp_view(position, size, bounds));
+
+    camera_transform.translation = position.extend(10f32);
+}
+
+/// Whether the camera shakes when the player gets hurt.
+#[derive(Resource)]
+pub struct ScreenShakeEnabled(pub bool);
+
+/// How hard the camera is shaking, from 0 to 1. Decays over time.
+#[derive(Resource, Default)]
+pub struct ScreenShake {
+    pub trauma: f32,
+    /// The offset added to the camera this frame, so it can be taken back off.
+    offset: Vec3,
+}
+
+const SHAKE_DECAY: f32 = 1.5;
+const MAX_SHAKE_OFFSET: f32 = 6.;
+
+/// Sent when the player hits the ground at the end of a slam.
+pub struct SlamLanded {
+    /// Where the player's feet hit the ground.
+    pub position: Vec2,
+    /// How hard the player landed, from 0 to 1.
+    pub intensity: f32,
+}
+
+/// How fast, in pixels per second, a slam has to be falling to land at full intensity.
+const FULL_SLAM_SPEED: f32 = 600.;
+const SLAM_DUST: usize = 12;
+
+/// Throws up dust and kicks the camera where a slam lands, harder the faster the player was falling.
+fn slam_impact(
+    mut commands: Commands,
+    mut landed: EventReader<SlamLanded>,
+    mut shake: ResMut<ScreenShake>,
+    mut rng: ResMut<GameRng>,
+    quality: Res<ParticleQuality>,
+) {
+    for SlamLanded { position, intensity } in landed.iter() {
+        shake.trauma = (shake.trauma + 0.2 + 0.4 * intensity).min(1.0);
+
+        let count = quality.scale((SLAM_DUST as f32 * intensity).ceil() as usize);
+        for i in 0..count {
+            // Alternate sides, so the dust spreads out both ways along the ground
+            let side = if i % 2 == 0 { 1. } else { -1. };
+            let speed = (40. + rng.next_f32() * 80.) * (0.5 + intensity);
+
+            commands.spawn((
+                SimpleMotion {
+                    velocity: Vec2::new(side * speed, 10. + rng.next_f32() * 30.),
+                    gravity: 100.,
+                },
+                Lifetime::from_seconds(0.3 + rng.next_f32() * 0.2),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0.8, 0.75, 0.65, 0.8),
+                        custom_size: Some(Vec2::splat(2.)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(layers::EFFECTS)),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+/// What a slam landing does to enemies around it.
+#[derive(Resource)]
+pub struct SlamAttack {
+    /// How far, in pixels, from the landing enemies are hit.
+    pub radius: f32,
+    /// Health taken from each enemy hit. Hurting them counts against the no-damage run.
+    pub damage: i32,
+    /// How hard enemies are pushed away, at full intensity.
+    pub knockback: f32,
+}
+
+impl Default for SlamAttack {
+    fn default() -> Self {
+        Self {
+            radius: 48.,
+            damage: 1,
+            knockback: 300.,
+        }
+    }
+}
+
+/// Slams chained onto enemies without touching the ground in between.
+#[derive(Resource, Default)]
+pub struct SlamCombo {
+    pub current: u32,
+    /// The longest chain this run, shown on the win screen.
+    pub best: u32,
+}
+
+impl SlamCombo {
+    fn hit(&mut self) {
+        self.current += 1;
+        self.best = self.best.max(self.current);
+    }
+}
+
+fn slam_attack(
+    mut commands: Commands,
+    mut landed: EventReader<SlamLanded>,
+    mut enemies: Query<(Entity, &GlobalTransform, &mut Velocity), With<Enemy>>,
+    attack: Res<SlamAttack>,
+    mut combo: ResMut<SlamCombo>,
 ) {
-    if let Ok(mut camera_transform) = camera_transform.get_single_mut() {
-        if let Ok(player_transform) = player_transform.get_single() {
-            let player_pos = player_transform.translation;
-            camera_transform.translation = Vec3::new(player_pos.x, player_pos.y + 75.0, 10f32);
+    for SlamLanded { position, intensity } in landed.iter() {
+        let mut hit = false;
+
+        for (entity, transform, mut velocity) in enemies.iter_mut() {
+            let offset = transform.translation().truncate() - *position;
+            if offset.length() > attack.radius {
+                continue;
+            }
+            hit = true;
+
+            // Knock enemies away and up off the ground
+            let direction = (offset.normalize_or_zero() + Vec2::Y).normalize_or_zero();
+            velocity.linvel += direction * attack.knockback * intensity.max(0.5);
+
+            if attack.damage > 0 {
+                commands
+                    .entity(entity)
+                    .insert(HealthEffect { amount: -attack.damage })
+                    .insert(DamageFlash::default());
+            }
+        }
+
+        if hit {
+            combo.hit();
+        } else {
+            combo.current = 0;
         }
     }
 }
 
-fn update_viewport(mut cameras: Query<&mut Camera, With<MainCamera>>, windows: Query<&Window>) {
-    let Ok(mut camera) = cameras.get_single_mut() else { return };
+#[derive(Component)]
+struct ComboText;
+
+fn update_combo_ui(
+    mut text: Query<(&mut Text, &mut Visibility), With<ComboText>>,
+    combo: Res<SlamCombo>,
+    font: Res<StandardFont>,
+) {
+    let Ok((mut text, mut visibility)) = text.get_single_mut() else { return };
+    if !combo.is_changed() && !text.sections.is_empty() {
+        return;
+    }
+
+    // A single slam isn't a chain yet
+    if combo.current < 2 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    *visibility = Visibility::Inherited;
+    *text = Text::from_section(
+        format!("Combo x{}", combo.current),
+        TextStyle {
+            font: font.0.clone(),
+            font_size: 20.0,
+            color: Color::ORANGE,
+        },
+    )
+    .with_alignment(TextAlignment::Center);
+}
+
+fn add_damage_shake(mut damaged: EventReader<PlayerDamaged>, mut shake: ResMut<ScreenShake>) {
+    for _ in damaged.iter() {
+        shake.trauma = (shake.trauma + 0.5).min(1.0);
+    }
+}
+
+/// Takes last frame's shake back off the camera, so it never drifts from where it should be.
+fn reset_screen_shake(
+    mut shake: ResMut<ScreenShake>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else { return };
+
+    camera.translation -= shake.offset;
+    shake.offset = Vec3::ZERO;
+}
+
+fn screen_shake(
+    mut shake: ResMut<ScreenShake>,
+    enabled: Res<ScreenShakeEnabled>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+) {
+    if !enabled.0 {
+        shake.trauma = 0.;
+        return;
+    }
+
+    if *game_state != GameState::Gameplay || shake.trauma <= 0. {
+        return;
+    }
+
+    let Ok(mut camera) = camera.get_single_mut() else { return };
+
+    // Squaring the trauma makes small hits subtle and big ones violent
+    let amount = shake.trauma * shake.trauma * MAX_SHAKE_OFFSET;
+    let offset = Vec3::new(rng.next_f32() * 2. - 1., rng.next_f32() * 2. - 1., 0.) * amount;
+
+    shake.offset = offset.round();
+    camera.translation += shake.offset;
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.);
+}
+
+/// When enabled, the camera frames the whole level instead of following the player.
+#[derive(Resource)]
+pub struct LevelOverview(pub bool);
+
+fn toggle_level_overview(
+    mut overview: ResMut<LevelOverview>,
+    keys: Res<Input<KeyCode>>,
+    game_state: Res<GameState>,
+) {
+    if !game_state.in_run() {
+        overview.0 = false;
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::M) {
+        overview.0 = !overview.0;
+    }
+}
+
+fn level_overview(
+    overview: Res<LevelOverview>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    level: Query<(&Handle<LdtkLevel>, &GlobalTransform)>,
+    levels: Res<Assets<LdtkLevel>>,
+) {
+    if !overview.0 {
+        return;
+    }
+
+    let Ok(mut camera) = camera.get_single_mut() else { return };
+    let Ok((level_handle, level_transform)) = level.get_single() else { return };
+    let Some(level) = levels.get(level_handle) else { return };
+
+    let size = Vec2::new(level.level.px_wid as f32, level.level.px_hei as f32);
+    let center = level_transform.translation().truncate() + size / 2.;
+
+    let zoom = (size.x / 480.).max(size.y / 320.).max(1.0);
+
+    camera.translation = center.extend(camera.translation.z);
+    camera.scale = Vec3::new(zoom, zoom, 1.0);
+}
+
+fn update_viewport(
+    mut cameras: Query<(&mut Camera, &mut Transform), With<MainCamera>>,
+    windows: Query<&Window>,
+    zoom: Res<CameraZoom>,
+) {
+    let Ok((mut camera, mut transform)) = cameras.get_single_mut() else { return };
     let Ok(window) = windows.get_single() else { return };
 
     let res = window.resolution.clone();
@@ -279,93 +1476,232 @@ fn update_viewport(mut cameras: Query<&mut Camera, With<MainCamera>>, windows: Q
     let h_scale = height / 320;
 
     let scale = if w_scale < h_scale { w_scale } else { h_scale };
+    let scale = scale.max(1);
 
     let i_width = 480 * scale;
     let i_height = 320 * scale;
 
-    let x = (width - i_width) / 2;
-    let y = (height - i_height) / 2;
+    let x = (width.saturating_sub(i_width)) / 2;
+    let y = (height.saturating_sub(i_height)) / 2;
 
     camera.viewport = Some(Viewport {
         physical_position: UVec2::new(x, y),
         physical_size: UVec2::new(i_width, i_height),
         depth: 0f32..1f32,
     });
+
+    // Scaling the camera shows more of the world, and since the UI is parented to the camera,
+    // it stays the same size on screen. Snap to a zoom that keeps whole screen pixels per world
+    // pixel when the viewport is large enough to allow it.
+    let pixels = scale as f32 / zoom.0;
+    let zoom = if pixels >= 1.0 {
+        scale as f32 / pixels.round()
+    } else {
+        zoom.0
+    };
+
+    transform.scale = Vec3::new(zoom, zoom, 1.0);
 }
 
-const GROUND_FORCE: f32 = 5000f32;
-const AIR_FORCE: f32 = 2500f32;
+/// Lets the player jump a second time in the air.
+#[derive(Resource)]
+pub struct DoubleJumpEnabled(pub bool);
+
+/// Jumps the player gets between landings when double jumping is enabled.
+const MAX_JUMPS: u32 = 2;
+
+/// Seconds between movement updates, so movement doesn't depend on the frame rate.
+const MOVEMENT_STEP: f32 = 1. / 60.;
+
+const GROUND_FORCE: f32 = 1500f32;
+const AIR_FORCE: f32 = 1000f32;
 const JUMP_IMPULSE: f32 = 1000f32;
 const SLAM_FORCE: f32 = 5000f32;
+/// How hard a wall jump pushes the player away from the wall.
+const WALL_JUMP_KICK: f32 = 400f32;
+/// How long horizontal input is ignored after a wall jump, so the player actually leaves the wall.
+const WALL_JUMP_LOCK: f32 = 0.15;
+/// How hard a dash launches the player, which decides how far they go.
+const DASH_IMPULSE: f32 = 600f32;
+const DASH_TIME: f32 = 0.15;
+const DASH_COOLDOWN: f32 = 0.75;
+
+/// The fastest the player can run by themselves. Impulses like dashes can go faster.
+const MAX_GROUND_SPEED: f32 = 200f32;
+const MAX_AIR_SPEED: f32 = 200f32;
+
+/// How long, in milliseconds, a jump pressed just before landing is remembered.
+const EARLY_JUMP_TIME: f64 = 40.0;
+/// How long, in milliseconds, the player can still jump after walking off a ledge.
+const COYOTE_TIME: f64 = 100.0;
+/// How fast the player moves up and down a ladder.
+const CLIMB_SPEED: f32 = 120f32;
 
-const MAX_GROUND_SPEED: f32 = 1500f32;
-const MAX_AIR_SPEED: f32 = 1000f32;
+/// Milliseconds since the Unix epoch, the same way on every platform.
+pub fn now_millis() -> f64 {
+    #[cfg(feature = "native")]
+    return SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0., |time| time.as_secs_f64() * 1000.);
 
-#[cfg(feature = "native")]
-const EARLY_JUMP_TIME: Duration = Duration::from_millis(40);
-#[cfg(feature = "browser")]
-const EARLY_JUMP_TIME: f64 = 40.0;
-#[cfg(feature = "native")]
-const COYOTE_TIME: Duration = Duration::from_millis(100);
-#[cfg(feature = "browser")]
-const COYOTE_TIME: f64 = 40.0;
+    #[cfg(feature = "browser")]
+    return Date::now();
+}
 
 const EASY_UP_GRAVITY: f32 = 9.81 * 25f32;
 const UP_GRAVITY: f32 = 9.81 * 100f32;
 const EASY_DOWN_GRAVITY: f32 = 9.81 * 200f32;
 const DOWN_GRAVITY: f32 = 9.81 * 275f32;
 
+/// Input for [`player_movement`], gathered every frame.
+/// Movement runs on a fixed timestep, which can skip frames or run more than once in one,
+/// so presses are held onto until a movement update uses them.
+#[derive(Resource, Default)]
+pub struct PlayerInput {
+    pub x: f32,
+    /// Up and down, for climbing ladders.
+    pub y: f32,
+    pub jump: bool,
+    pub just_jumped: bool,
+    pub crouch: bool,
+    /// Whether crouch is held down right now, for dropping through one-way platforms.
+    pub crouch_held: bool,
+    pub dash: bool,
+}
+
+fn read_player_input(mut input: ResMut<PlayerInput>, actions: ActionInput) {
+    input.x = actions.move_axis();
+    input.y = actions.climb_axis();
+    input.jump = actions.jump_pressed();
+
+    if actions.jump_just_pressed() {
+        input.just_jumped = true;
+    }
+    input.crouch_held = actions.crouch_pressed();
+
+    if actions.crouch_just_pressed() {
+        input.crouch = true;
+    }
+    if actions.dash_just_pressed() {
+        input.dash = true;
+    }
+}
+
 fn player_movement(
-    mut player: Query<(&mut Velocity, &mut TextureAtlasSprite, &mut PlayerPhysics), With<Player>>,
-    keys: Res<Input<KeyCode>>,
-    time: Res<Time>,
+    mut player: Query<
+        (
+            &mut Velocity,
+            &mut TextureAtlasSprite,
+            &mut PlayerPhysics,
+            Option<&PlayerBuff>,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
+    mut input: ResMut<PlayerInput>,
+    mut slammed: EventWriter<PlayerSlammed>,
+    double_jump: Res<DoubleJumpEnabled>,
+    fixed_time: Res<FixedTime>,
     state: Res<GameState>,
+    hit_stop: Res<HitStop>,
 ) {
+    // Presses made during a hit-stop are kept for when it ends
+    if hit_stop.active() {
+        return;
+    }
+
+    // Presses are used up even when they can't do anything, so they don't go off after unpausing
+    let just_jumped = std::mem::take(&mut input.just_jumped);
+    let mut crouch = std::mem::take(&mut input.crouch);
+    let dash = std::mem::take(&mut input.dash);
+
     if *state != GameState::Gameplay {
         return;
     };
-    let Ok((mut velocity, mut sprite, mut physics)) = player.get_single_mut() else { return };
-    #[cfg(feature = "native")]
-    let now = Instant::now();
-    #[cfg(feature = "browser")]
-    let now = Date::now();
-    let prev_velocity = velocity.linvel.clone();
+    let Ok((mut velocity, mut sprite, mut physics, buff)) = player.get_single_mut() else { return };
+    let now = now_millis();
+    let delta = fixed_time.period.as_secs_f32();
+    let mut prev_velocity = velocity.linvel.clone();
     let mut new_velocity = Vec2::ZERO;
     let mut new_impulse = Vec2::ZERO;
-    let mut x_input = 0f32;
-    let mut just_jumped = false;
-    let mut jump = false;
-    let mut crouch = false;
+    let mut x_input = input.x;
+    let jump = input.jump;
 
-    if keys.pressed(KeyCode::D) {
-        x_input += 1.;
-    }
-    if keys.pressed(KeyCode::A) {
-        x_input -= 1.;
+    if physics.wall_jump_lock > 0. {
+        physics.wall_jump_lock -= delta;
+        x_input = 0.;
     }
-    if keys.just_pressed(KeyCode::Space) {
-        just_jumped = true;
+
+    physics.dash_cooldown = (physics.dash_cooldown - delta).max(0.);
+    if physics.grounded {
+        physics.dashed_in_air = false;
     }
-    if keys.pressed(KeyCode::Space) {
-        jump = true;
+
+    let dash_direction = if sprite.flip_x { -1. } else { 1. };
+
+    if dash && physics.dash_cooldown <= 0. && !physics.dashed_in_air {
+        prev_velocity = Vec2::ZERO;
+        new_impulse.x += dash_direction * DASH_IMPULSE;
+        physics.dash_time = DASH_TIME;
+        physics.dash_cooldown = DASH_COOLDOWN;
+        physics.dashed_in_air = !physics.grounded;
+        physics.slamming = false;
+    } else if physics.dash_time > 0. {
+        physics.dash_time -= delta;
+
+        // Running into a wall ends the dash instead of grinding against it
+        if physics.touching_wall == Some(-dash_direction) {
+            physics.dash_time = 0.;
+            prev_velocity.x = 0.;
+        }
     }
-    if keys.just_pressed(KeyCode::LControl) {
-        crouch = true;
+
+    let dashing = physics.dash_time > 0.;
+    if dashing {
+        // Keep the dash straight
+        x_input = 0.;
+        crouch = false;
     }
 
     if x_input != 0. {
         sprite.flip_x = x_input.is_sign_negative();
     }
 
+    let jump_boost = buff.map_or(1.0, |buff| buff.kind.jump());
+
+    // Leaving the ladder, walking off it once at the bottom, or dashing lets go
+    if physics.climbing && (!physics.on_ladder || dashing || (physics.grounded && x_input != 0.)) {
+        physics.climbing = false;
+    }
+    // Grabbing on waits until a jump has slowed down, so jumping off doesn't catch the ladder again
+    if !physics.climbing
+        && physics.on_ladder
+        && input.y != 0.
+        && !dashing
+        && prev_velocity.y <= CLIMB_SPEED
+    {
+        physics.climbing = true;
+        physics.slamming = false;
+    }
+
+    if physics.climbing {
+        if just_jumped {
+            physics.climbing = false;
+            physics.jumps_remaining = MAX_JUMPS - 1;
+            velocity.linvel = Vec2::new(0., JUMP_IMPULSE * jump_boost);
+        } else {
+            // No gravity or drifting sideways, only straight up and down
+            velocity.linvel = Vec2::new(0., input.y * CLIMB_SPEED);
+        }
+        return;
+    }
+
+    let speed = buff.map_or(1.0, |buff| buff.kind.speed());
+
     let mut max_speed = MAX_GROUND_SPEED;
 
     let mut is_early_jump = false;
     if let Some(early_jump) = physics.early_jump {
-        #[cfg(feature = "native")]
-        let val = Instant::now() - early_jump < EARLY_JUMP_TIME;
-        #[cfg(feature = "browser")]
-        let val = Date::now() - early_jump < EARLY_JUMP_TIME;
-        if val {
+        if now - early_jump < EARLY_JUMP_TIME {
             is_early_jump = true;
         } else {
             physics.early_jump = None;
@@ -374,11 +1710,7 @@ fn player_movement(
 
     let mut is_coyote_time = false;
     if let Some(coyote_time) = physics.coyote_time {
-        #[cfg(feature = "native")]
-        let val = Instant::now() - coyote_time < COYOTE_TIME;
-        #[cfg(feature = "browser")]
-        let val = Date::now() - coyote_time < COYOTE_TIME;
-        if val {
+        if now - coyote_time < COYOTE_TIME {
             is_coyote_time = true;
         } else {
             physics.coyote_time = None;
@@ -387,61 +1719,109 @@ fn player_movement(
 
     if physics.grounded || is_coyote_time {
         if just_jumped || is_early_jump {
-            new_impulse.y += JUMP_IMPULSE;
+            new_impulse.y += JUMP_IMPULSE * jump_boost;
             physics.coyote_time = None;
+            physics.jumps_remaining = physics.jumps_remaining.saturating_sub(1);
         } else if physics.grounded {
             physics.coyote_time = Some(now);
         }
-        new_velocity.x += x_input * GROUND_FORCE;
+        new_velocity.x += x_input * GROUND_FORCE * speed;
         physics.slamming = false;
     } else {
         if crouch || physics.slamming {
             new_velocity.y -= SLAM_FORCE;
             if crouch {
                 physics.slamming = true;
+                physics.jumps_remaining = 0;
+                slammed.send(PlayerSlammed);
             }
+        } else if let (true, Some(normal)) = (just_jumped, physics.touching_wall) {
+            // Push off the wall, dropping whatever speed carried the player into it
+            prev_velocity.y = prev_velocity.y.max(0.);
+            prev_velocity.x = 0.;
+            new_impulse += Vec2::new(normal * WALL_JUMP_KICK, JUMP_IMPULSE * jump_boost);
+            physics.wall_jump_lock = WALL_JUMP_LOCK;
+            sprite.flip_x = normal < 0.;
+        } else if just_jumped && double_jump.0 && physics.jumps_remaining > 0 {
+            // Cancel the fall so the air jump always gives the same lift
+            prev_velocity.y = prev_velocity.y.max(0.);
+            new_impulse.y += JUMP_IMPULSE * jump_boost;
+            // Walking off a ledge still only gives one air jump
+            physics.jumps_remaining = 0;
         } else if just_jumped {
             physics.early_jump = Some(now);
         }
 
-        new_velocity.x += x_input * AIR_FORCE;
+        new_velocity.x += x_input * AIR_FORCE * speed;
         max_speed = MAX_AIR_SPEED;
 
-        if prev_velocity.y >= 0. {
-            if jump {
-                new_velocity.y -= EASY_UP_GRAVITY;
-            } else {
-                new_velocity.y -= UP_GRAVITY;
-            }
-        } else {
-            if jump {
-                new_velocity.y -= EASY_DOWN_GRAVITY;
+        // Dashes are horizontal, so gravity waits until they're over
+        if !dashing {
+            if prev_velocity.y >= 0. {
+                if jump {
+                    new_velocity.y -= EASY_UP_GRAVITY;
+                } else {
+                    new_velocity.y -= UP_GRAVITY;
+                }
             } else {
-                new_velocity.y -= DOWN_GRAVITY;
+                if jump {
+                    new_velocity.y -= EASY_DOWN_GRAVITY;
+                } else {
+                    new_velocity.y -= DOWN_GRAVITY;
+                }
             }
         }
     }
 
-    let max_speed = max_speed.max(prev_velocity.length());
+    velocity.linvel = step_velocity(prev_velocity, new_velocity, new_impulse, max_speed * speed, delta);
+}
+
+/// Applies one movement step's forces and impulses to the velocity.
+/// Movement runs on a fixed step, so `delta` is always the same however fast frames come.
+fn step_velocity(prev_velocity: Vec2, force: Vec2, impulse: Vec2, max_speed: f32, delta: f32) -> Vec2 {
+    let mut linvel = prev_velocity + force * delta + impulse;
 
-    let clamped_velocity = Vec2::new(new_velocity.x.clamp(-max_speed, max_speed), new_velocity.y);
+    // Running can't push past the top speed, but it doesn't cancel extra speed from impulses either,
+    // which damping wears down instead
+    let limit = max_speed.max((prev_velocity.x + impulse.x).abs());
+    linvel.x = linvel.x.clamp(-limit, limit);
 
-    velocity.linvel = clamped_velocity * time.delta_seconds() + prev_velocity + new_impulse;
+    linvel
 }
 
+/// How long the player can't be hurt again after taking damage,
+/// so standing in an enemy or a hazard doesn't drain all their health at once.
+const DAMAGE_INVULNERABILITY: Duration = Duration::from_millis(1000);
+
 fn player_physics_checks(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
-    mut player: Query<(Entity, &mut PlayerPhysics)>,
+    mut player: Query<(
+        Entity,
+        &mut PlayerPhysics,
+        &GlobalTransform,
+        &Velocity,
+        Option<&Invulnerable>,
+    )>,
     mut health: ResMut<PlayerHealth>,
     ground_sensor: Query<Entity, With<PlayerGroundSensor>>,
     damage_sensor: Query<Entity, With<PlayerDamageSensor>>,
-    damage_activator: Query<(&Parent, &EnemyDamageActivator)>,
+    left_sensor: Query<Entity, With<PlayerSensorLeft>>,
+    right_sensor: Query<Entity, With<PlayerSensorRight>>,
+    damage_activator: Query<(Option<&Parent>, &EnemyDamageActivator, &GlobalTransform)>,
     damage_effect: Query<&DamageEffect>,
+    mut damaged: EventWriter<PlayerDamaged>,
+    mut slam_landed: EventWriter<SlamLanded>,
+    mut combo: ResMut<SlamCombo>,
 ) {
-    let Ok((entity, mut physics)) = player.get_single_mut() else { return };
+    let Ok((entity, mut physics, player_transform, velocity, invulnerable)) = player.get_single_mut() else { return };
     let Ok(ground_sensor) = ground_sensor.get_single() else { return };
     let Ok(damage_sensor) = damage_sensor.get_single() else { return };
+    let Ok(left_sensor) = left_sensor.get_single() else { return };
+    let Ok(right_sensor) = right_sensor.get_single() else { return };
+
+    // Tracked here too, so several activators touching in the same frame only hurt once
+    let mut invulnerable = invulnerable.is_some();
 
     for collision_event in collision_events.iter() {
         match collision_event {
@@ -451,13 +1831,39 @@ fn player_physics_checks(
                 };
 
                 if *a == ground_sensor || *b == ground_sensor {
+                    // Climbing past a floor isn't landing on it, but it still has to be counted,
+                    // or the total would be off once the player lets go
+                    let landing = !physics.grounded && !physics.climbing;
+                    if landing && physics.slamming {
+                        slam_landed.send(SlamLanded {
+                            position: player_transform.translation().truncate() - Vec2::Y * 16.,
+                            intensity: (-velocity.linvel.y / FULL_SLAM_SPEED).clamp(0., 1.),
+                        });
+                    } else if landing {
+                        // Touching down without a slam breaks the chain
+                        combo.current = 0;
+                    }
+
                     physics.total_ground_collisions += 1;
                     if physics.total_ground_collisions > 0 {
                         physics.grounded = true;
+                        physics.jumps_remaining = MAX_JUMPS;
                     }
                     continue;
                 }
 
+                if *a == left_sensor || *b == left_sensor {
+                    physics.left_wall_collisions += 1;
+                    physics.update_touching_wall();
+                    continue;
+                }
+
+                if *a == right_sensor || *b == right_sensor {
+                    physics.right_wall_collisions += 1;
+                    physics.update_touching_wall();
+                    continue;
+                }
+
                 let activator = if *a == damage_sensor {
                     b
                 } else if *b == damage_sensor {
@@ -466,17 +1872,31 @@ fn player_physics_checks(
                     continue;
                 };
 
-                let Ok((parent, activator)) = damage_activator.get(*activator) else { continue };
+                let Ok((parent, damage, activator_transform)) = damage_activator.get(*activator) else { continue };
+
+                if invulnerable {
+                    continue;
+                }
 
-                let effect = damage_effect.get(**parent);
+                // Projectiles hurt by themselves, while melee activators are part of their enemy
+                let owner = parent.map_or(*activator, |parent| parent.get());
+                let effect = damage_effect.get(owner);
 
                 let multiplier = match effect {
                     Ok(effect) => effect.multiplier,
                     Err(_) => 1.0,
                 };
 
-                health.0 += (activator.0 as f32 * multiplier) as i32;
-                commands.entity(entity).insert(DamageFlash::default());
+                health.0 += (damage.0 as f32 * multiplier) as i32;
+                commands
+                    .entity(entity)
+                    .insert((DamageFlash::default(), Invulnerable::new(DAMAGE_INVULNERABILITY)));
+                invulnerable = true;
+
+                let direction = (activator_transform.translation() - player_transform.translation())
+                    .truncate()
+                    .normalize_or_zero();
+                damaged.send(PlayerDamaged { direction });
             }
             CollisionEvent::Stopped(a, b, flags) => {
                 if *flags & CollisionEventFlags::SENSOR != CollisionEventFlags::SENSOR {
@@ -489,37 +1909,139 @@ fn player_physics_checks(
                         physics.grounded = false;
                     }
                 }
+
+                if *a == left_sensor || *b == left_sensor {
+                    physics.left_wall_collisions -= 1;
+                    physics.update_touching_wall();
+                }
+
+                if *a == right_sensor || *b == right_sensor {
+                    physics.right_wall_collisions -= 1;
+                    physics.update_touching_wall();
+                }
             }
         }
     }
 }
 
+/// Keeps [`PlayerPhysics::on_ladder`] up to date, for [`player_movement`] to start and stop climbing with.
+fn ladder_checks(
+    mut player: Query<(Entity, &mut PlayerPhysics), With<Player>>,
+    ladders: Query<(), With<Ladder>>,
+    rapier_context: Res<RapierContext>,
+) {
+    let Ok((entity, mut physics)) = player.get_single_mut() else { return };
+
+    physics.on_ladder = rapier_context
+        .intersections_with(entity)
+        .filter(|(_, _, intersecting)| *intersecting)
+        .any(|(a, b, _)| ladders.contains(if a == entity { b } else { a }));
+}
+
+fn hazard_damage(
+    mut commands: Commands,
+    player: Query<(Entity, &GlobalTransform, Option<&Invulnerable>), (With<Player>, Without<Dying>)>,
+    damage_sensor: Query<Entity, With<PlayerDamageSensor>>,
+    hazards: Query<(&Hazard, &GlobalTransform)>,
+    rapier_context: Res<RapierContext>,
+    mut health: ResMut<PlayerHealth>,
+    mut damaged: EventWriter<PlayerDamaged>,
+    game_state: Res<GameState>,
+) {
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    let Ok((entity, player_transform, invulnerable)) = player.get_single() else { return };
+    let Ok(damage_sensor) = damage_sensor.get_single() else { return };
+
+    if invulnerable.is_some() || health.0 <= 0 {
+        return;
+    }
+
+    // Checked every frame rather than on contact, so standing in a hazard keeps hurting once the i-frames end
+    let hazard = rapier_context
+        .intersections_with(damage_sensor)
+        .filter(|(_, _, intersecting)| *intersecting)
+        .find_map(|(a, b, _)| hazards.get(if a == damage_sensor { b } else { a }).ok());
+
+    let Some((hazard, hazard_transform)) = hazard else { return };
+
+    match hazard.damage {
+        HazardDamage::Chip(amount) => health.0 += amount,
+        HazardDamage::InstantKill => health.0 = 0,
+    }
+
+    commands
+        .entity(entity)
+        .insert((DamageFlash::default(), Invulnerable::new(DAMAGE_INVULNERABILITY)));
+
+    let direction = (hazard_transform.translation() - player_transform.translation())
+        .truncate()
+        .normalize_or_zero();
+    damaged.send(PlayerDamaged { direction });
+}
+
+/// Plays out the player's death before the game over screen appears.
+#[derive(Component)]
+pub struct Dying(Timer);
+
+impl Default for Dying {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0.75, TimerMode::Once))
+    }
+}
+
 fn game_over(
+    mut commands: Commands,
     health: Res<PlayerHealth>,
     mut game_state: ResMut<GameState>,
+    mut player: Query<
+        (
+            Entity,
+            &mut LockedAxes,
+            &mut Velocity,
+            &TextureAtlasSprite,
+            Option<&mut Dying>,
+        ),
+        With<Player>,
+    >,
+    time: Res<Time>,
 ) {
     if health.0 > 0 {
         return;
     };
-    if *game_state != GameState::GameOver {
+    if *game_state != GameState::Gameplay {
+        return;
+    }
+
+    let Ok((entity, mut locked_axes, mut velocity, sprite, dying)) = player.get_single_mut() else {
         *game_state = GameState::GameOver;
+        return;
+    };
+
+    if let Some(mut dying) = dying {
+        dying.0.tick(time.delta());
+        if dying.0.finished() {
+            *game_state = GameState::GameOver;
+        }
+    } else {
+        // Let the player tumble away from the direction they were facing
+        *locked_axes = LockedAxes::empty();
+        velocity.linvel = Vec2::new(if sprite.flip_x { 150. } else { -150. }, 300.);
+        velocity.angvel = if sprite.flip_x { -10. } else { 10. };
+        commands.entity(entity).insert(Dying::default());
     }
 }
 
 fn update_player_health_ui(
     health: Res<PlayerHealth>,
-    mut heart_0: Query<&mut Handle<Image>, With<Heart<0>>>,
-    mut heart_1: Query<&mut Handle<Image>, (With<Heart<1>>, Without<Heart<0>>)>,
-    mut heart_2: Query<&mut Handle<Image>, (With<Heart<2>>, Without<Heart<1>>, Without<Heart<0>>)>,
+    mut hearts: Query<(&mut Handle<Image>, &HeartSlot)>,
     heart_images: Res<HeartImages>,
     time: Res<Time>,
     mut stopwatch: Local<Stopwatch>,
     mut flash: Local<bool>,
 ) {
-    let Ok(mut heart_0) = heart_0.get_single_mut() else { return };
-    let Ok(mut heart_1) = heart_1.get_single_mut() else { return };
-    let Ok(mut heart_2) = heart_2.get_single_mut() else { return };
-
     let hp = health.0;
 
     stopwatch.tick(time.delta());
@@ -533,36 +2055,6 @@ fn update_player_health_ui(
         stopwatch.reset();
     }
 
-    let (hp0, hp1, hp2) = (
-        {
-            if hp > 1 {
-                2
-            } else if hp == 1 {
-                1
-            } else {
-                0
-            }
-        },
-        {
-            if hp > 3 {
-                2
-            } else if hp == 3 {
-                1
-            } else {
-                0
-            }
-        },
-        {
-            if hp > 5 {
-                2
-            } else if hp == 5 {
-                1
-            } else {
-                0
-            }
-        },
-    );
-
     let empty = heart_images.empty.clone();
     let half = match *flash {
         false => heart_images.half.clone(),
@@ -573,36 +2065,85 @@ fn update_player_health_ui(
         true => heart_images.full_flash.clone(),
     };
 
-    *heart_0 = match hp0 {
-        0 => empty.clone(),
-        1 => half.clone(),
-        _ => full.clone(),
-    };
+    for (mut image, slot) in hearts.iter_mut() {
+        *image = match slot.fill(hp) {
+            0 => empty.clone(),
+            1 => half.clone(),
+            _ => full.clone(),
+        };
+    }
+}
 
-    *heart_1 = match hp1 {
-        0 => empty.clone(),
-        1 => half.clone(),
-        _ => full.clone(),
-    };
+/// How low, in pixels, the player falls before moving on to the next level,
+/// unless the level sets its own "ExitHeight" field in LDtk.
+const DEFAULT_EXIT_HEIGHT: f32 = 128.;
 
-    *heart_2 = match hp2 {
-        0 => empty.clone(),
-        1 => half.clone(),
-        _ => full.clone(),
-    };
-}
+/// Set while the next level loads, so falling out of a level only switches once.
+#[derive(Resource, Default)]
+struct Transitioning(bool);
 
 fn switch_levels(
     mut commands: Commands,
     player: Query<&Transform, With<Player>>,
+    spawned: Query<(), Added<Player>>,
+    level: Query<&Handle<LdtkLevel>>,
+    levels: Res<Assets<LdtkLevel>>,
     mut level_selection: ResMut<LevelSelection>,
+    mut transitioning: ResMut<Transitioning>,
     world: Query<Entity, With<WorldCollider>>,
+    mut time_bonus: EventWriter<TimeBonus>,
+    level_count: Res<LevelCount>,
+    mut game_state: ResMut<GameState>,
+    mode: Res<GameMode>,
+    mut win_reason: ResMut<WinReason>,
+    timer: Res<GameTimer>,
+    mut score: EventWriter<ScoreEvent>,
 ) {
+    // The next level's player spawning means it's finished loading
+    if !spawned.is_empty() {
+        transitioning.0 = false;
+    }
+    if transitioning.0 {
+        return;
+    }
+
     let Ok(player) = player.get_single() else { return };
 
-    if player.translation.y < 128.0 {
+    let exit_height = level
+        .get_single()
+        .ok()
+        .and_then(|handle| levels.get(handle))
+        .and_then(|level| match field_value(&level.level.field_instances, "ExitHeight") {
+            Some(FieldValue::Int(Some(height))) => Some(*height as f32),
+            Some(FieldValue::Float(Some(height))) => Some(*height),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_EXIT_HEIGHT);
+
+    if player.translation.y < exit_height {
+        // Waves are fought in the one level, so falling out of it ends the run instead of moving on
+        if *mode == GameMode::Waves {
+            if *game_state == GameState::Gameplay {
+                *game_state = GameState::GameOver;
+            }
+            return;
+        }
+
         let LevelSelection::Index(i) = &mut *level_selection else { return };
+
+        // There's nowhere left to go, so leaving the last level finishes the campaign
+        if level_count.is_final(*i) {
+            if *mode == GameMode::Campaign && *game_state == GameState::Gameplay {
+                *game_state = GameState::WinScreen;
+                *win_reason = WinReason::OutOfLevels;
+                score.send(ScoreEvent(score::time_points(&timer)));
+            }
+            return;
+        }
+
         *i += 1;
+        transitioning.0 = true;
+        time_bonus.send(TimeBonus(LevelScaling { level: *i }.time_bonus()));
         for collider in world.iter() {
             commands.entity(collider).despawn();
         }
@@ -615,6 +2156,7 @@ fn update_timer(
     time: Res<Time>,
     font: Res<StandardFont>,
     mut game_state: ResMut<GameState>,
+    mode: Res<GameMode>,
 ) {
     if *game_state != GameState::Gameplay {
         return;
@@ -622,9 +2164,15 @@ fn update_timer(
 
     let Ok(mut timer_ui) = timer_ui.get_single_mut() else { return };
 
-    timer.0.tick(time.delta());
+    // Wave mode lasts for as long as the player survives, so there's no clock
+    if *mode == GameMode::Waves {
+        timer_ui.sections.clear();
+        return;
+    }
 
-    let remaining = timer.0.remaining_secs();
+    timer.tick(time.delta());
+
+    let remaining = timer.remaining_secs();
 
     let minutes = (remaining / 60.0) as u32;
     let seconds = (remaining % 60.0) as u32;
@@ -648,9 +2196,327 @@ fn update_timer(
     *timer_ui = Text::from_section(format!("{:0>2}:{:0>2}", minutes, seconds), style)
         .with_alignment(TextAlignment::Center);
 
-    if timer.0.finished() {
+    if timer.finished() {
         if *game_state != GameState::GameOver {
             *game_state = GameState::GameOver;
         }
     }
 }
+
+/// Text that fades out as it drifts down, over its [`Lifetime`].
+#[derive(Component)]
+pub struct FloatingText;
+
+fn apply_time_bonus(
+    mut commands: Commands,
+    mut bonuses: EventReader<TimeBonus>,
+    mut timer: ResMut<GameTimer>,
+    ui: Query<Entity, With<PlayerUi>>,
+    font: Res<StandardFont>,
+    quality: Res<ParticleQuality>,
+    mode: Res<GameMode>,
+) {
+    if *mode == GameMode::Waves {
+        bonuses.clear();
+        return;
+    }
+
+    for TimeBonus(time) in bonuses.iter() {
+        timer.add_time(*time);
+
+        if *quality == ParticleQuality::Off {
+            continue;
+        }
+
+        let Ok(ui) = ui.get_single() else { continue };
+
+        commands.entity(ui).with_children(|parent| {
+            parent.spawn((
+                FloatingText,
+                Lifetime::from_seconds(1.0),
+                SimpleMotion {
+                    velocity: Vec2::new(0., -16.),
+                    ..default()
+                },
+                Text2dBundle {
+                    text: Text::from_section(
+                        format!("+{}s", time.as_secs()),
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: 16.0,
+                            color: Color::GREEN,
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    transform: Transform::from_xyz(40., 150., layers::HUD),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+fn record_damage_taken(mut damaged: EventReader<PlayerDamaged>, mut damage_taken: ResMut<DamageTaken>) {
+    if !damaged.is_empty() {
+        damaged.clear();
+        damage_taken.0 = true;
+    }
+}
+
+fn update_floating_text(mut texts: Query<(&Lifetime, &mut Text), With<FloatingText>>) {
+    for (lifetime, mut text) in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(lifetime.0.percent_left());
+        }
+    }
+}
+
+/// Sent when an enemy or hazard hurts the player.
+pub struct PlayerDamaged {
+    /// Points from the player towards whatever dealt the damage.
+    pub direction: Vec2,
+}
+
+#[derive(Resource)]
+struct IndicatorAssets {
+    mesh: Handle<Mesh>,
+    damage_material: Handle<ColorMaterial>,
+    enemy_material: Handle<ColorMaterial>,
+}
+
+/// Places a point on the edge of the screen in the given direction, relative to the center.
+fn screen_edge(direction: Vec2) -> (Vec3, Quat) {
+    let scale = (224. / direction.x.abs()).min(144. / direction.y.abs());
+    let position = (direction * scale).extend(layers::HUD);
+    let rotation = Quat::from_rotation_z(direction.y.atan2(direction.x) - std::f32::consts::FRAC_PI_2);
+    (position, rotation)
+}
+
+#[derive(Component)]
+struct DamageIndicator(Timer);
+
+fn spawn_damage_indicators(
+    mut commands: Commands,
+    mut damaged: EventReader<PlayerDamaged>,
+    ui: Query<Entity, With<PlayerUi>>,
+    assets: Res<IndicatorAssets>,
+) {
+    let Ok(ui) = ui.get_single() else { return };
+
+    for PlayerDamaged { direction } in damaged.iter() {
+        if *direction == Vec2::ZERO {
+            continue;
+        }
+
+        let (position, rotation) = screen_edge(*direction);
+
+        commands.entity(ui).with_children(|parent| {
+            parent.spawn((
+                DamageIndicator(Timer::from_seconds(0.5, TimerMode::Once)),
+                ColorMesh2dBundle {
+                    mesh: assets.mesh.clone().into(),
+                    material: assets.damage_material.clone(),
+                    transform: Transform::from_translation(position).with_rotation(rotation),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+fn update_damage_indicators(
+    mut commands: Commands,
+    mut indicators: Query<(Entity, &mut DamageIndicator, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut indicator, mut transform) in indicators.iter_mut() {
+        indicator.0.tick(time.delta());
+
+        transform.scale = Vec3::splat(indicator.0.percent_left());
+
+        if indicator.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+const MAX_ENEMY_INDICATORS: usize = 4;
+
+#[derive(Component)]
+struct EnemyIndicator;
+
+fn update_enemy_indicators(
+    mut commands: Commands,
+    camera: Query<&Transform, With<MainCamera>>,
+    enemies: Query<&GlobalTransform, With<Enemy>>,
+    mut indicators: Query<(Entity, &mut Transform), (With<EnemyIndicator>, Without<MainCamera>)>,
+    ui: Query<Entity, With<PlayerUi>>,
+    assets: Res<IndicatorAssets>,
+) {
+    let Ok(camera) = camera.get_single() else { return };
+    let Ok(ui) = ui.get_single() else { return };
+
+    let center = camera.translation.truncate();
+    let (half_width, half_height) = (240. * camera.scale.x, 160. * camera.scale.y);
+
+    let mut offscreen: Vec<Vec2> = enemies
+        .iter()
+        .map(|transform| transform.translation().truncate() - center)
+        .filter(|offset| offset.x.abs() > half_width || offset.y.abs() > half_height)
+        .collect();
+
+    // Only point out the closest enemies to avoid cluttering the screen
+    offscreen.sort_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+    offscreen.truncate(MAX_ENEMY_INDICATORS);
+
+    let mut offscreen = offscreen.into_iter();
+
+    for (entity, mut transform) in indicators.iter_mut() {
+        if let Some(offset) = offscreen.next() {
+            let (position, rotation) = screen_edge(offset.normalize());
+            transform.translation = position;
+            transform.rotation = rotation;
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    commands.entity(ui).with_children(|parent| {
+        for offset in offscreen {
+            let (position, rotation) = screen_edge(offset.normalize());
+            parent.spawn((
+                EnemyIndicator,
+                ColorMesh2dBundle {
+                    mesh: assets.mesh.clone().into(),
+                    material: assets.enemy_material.clone(),
+                    transform: Transform::from_translation(position)
+                        .with_rotation(rotation)
+                        .with_scale(Vec3::splat(0.75)),
+                    ..default()
+                },
+            ));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How far the player runs in a second from standing, with frames coming in at `fps`.
+    /// Frame time builds up and is spent in whole movement steps, the same as `FixedUpdate`.
+    fn run_distance(fps: u32) -> f32 {
+        let frame = Duration::from_secs(1) / fps;
+        let step = Duration::from_secs_f32(MOVEMENT_STEP);
+        let mut accumulated = Duration::ZERO;
+        let mut velocity = Vec2::ZERO;
+        let mut distance = 0.;
+
+        for _ in 0..fps {
+            accumulated += frame;
+            while accumulated >= step {
+                accumulated -= step;
+                velocity = step_velocity(velocity, Vec2::X * GROUND_FORCE, Vec2::ZERO, MAX_GROUND_SPEED, MOVEMENT_STEP);
+                distance += velocity.x * MOVEMENT_STEP;
+            }
+        }
+        distance
+    }
+
+    #[test]
+    fn running_is_frame_rate_independent() {
+        let slow = run_distance(30);
+        let fast = run_distance(144);
+
+        assert!(slow > 0.);
+        // Leftover frame time can hold back at most one step
+        assert!((slow - fast).abs() <= MAX_GROUND_SPEED * MOVEMENT_STEP, "{slow} vs {fast}");
+    }
+
+    #[test]
+    fn two_contacts_in_one_frame_hurt_once() {
+        let mut world = World::new();
+        world.insert_resource(PlayerHealth(6));
+        world.insert_resource(SlamCombo::default());
+        world.insert_resource(Events::<CollisionEvent>::default());
+        world.insert_resource(Events::<PlayerDamaged>::default());
+        world.insert_resource(Events::<SlamLanded>::default());
+
+        world.spawn((PlayerPhysics::default(), GlobalTransform::default(), Velocity::default()));
+        world.spawn(PlayerGroundSensor);
+        world.spawn(PlayerSensorLeft);
+        world.spawn(PlayerSensorRight);
+        let damage_sensor = world.spawn(PlayerDamageSensor).id();
+
+        for _ in 0..2 {
+            let activator = world.spawn((EnemyDamageActivator(-1), GlobalTransform::default())).id();
+            world
+                .resource_mut::<Events<CollisionEvent>>()
+                .send(CollisionEvent::Started(damage_sensor, activator, CollisionEventFlags::SENSOR));
+        }
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(player_physics_checks);
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<PlayerHealth>().0, 5);
+        assert_eq!(world.resource::<Events<PlayerDamaged>>().len(), 1);
+    }
+
+    #[test]
+    fn tap_between_frames_still_jumps() {
+        use bevy::input::{
+            keyboard::KeyboardInput,
+            mouse::{MouseButtonInput, MouseWheel},
+            ButtonState,
+        };
+
+        let mut world = World::new();
+        world.insert_resource(Input::<KeyCode>::default());
+        world.insert_resource(Input::<MouseButton>::default());
+        world.insert_resource(InputConfig::default());
+        world.insert_resource(PressLatch::default());
+        world.insert_resource(PlayerInput::default());
+        world.insert_resource(Gamepads::default());
+        world.insert_resource(Input::<GamepadButton>::default());
+        world.insert_resource(Axis::<GamepadAxis>::default());
+        world.insert_resource(Events::<KeyboardInput>::default());
+        world.insert_resource(Events::<MouseButtonInput>::default());
+        world.insert_resource(Events::<MouseWheel>::default());
+
+        // Both halves of the tap arrive before the frame, so the key no longer reads as held
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            world.resource_mut::<Events<KeyboardInput>>().send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(KeyCode::Space),
+                state,
+            });
+        }
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((latch_presses, read_player_input).chain());
+        schedule.run(&mut world);
+
+        let input = world.resource::<PlayerInput>();
+        assert!(input.just_jumped);
+        assert!(!input.jump);
+    }
+
+    #[test]
+    fn healing_back_to_full_still_counts_as_hurt() {
+        let mut world = World::new();
+        world.insert_resource(DamageTaken(false));
+        world.insert_resource(Events::<PlayerDamaged>::default());
+        world.resource_mut::<Events<PlayerDamaged>>().send(PlayerDamaged { direction: Vec2::X });
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(record_damage_taken);
+        schedule.run(&mut world);
+
+        // Like respawning at a checkpoint
+        world.insert_resource(PlayerHealth(6));
+        schedule.run(&mut world);
+
+        assert!(world.resource::<DamageTaken>().0);
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::*;
+
+use crate::{enemies::Enemy, layers, storage, world::StandardFont, GameState};
+
+use super::{
+    controls::{Action, InputConfig},
+    Player, PlayerPhysics, PlayerUi,
+};
+
+const TUTORIAL_FILE: &str = "tutorial";
+
+/// How far below the player there has to be nothing for them to count as over a gap.
+const GAP_DEPTH: f32 = 96.;
+
+/// Which tutorial prompts the player has already seen, so they only show once.
+#[derive(Resource, Default)]
+pub struct TutorialSeen {
+    pub slam: bool,
+}
+
+impl TutorialSeen {
+    fn save(&self) {
+        let mut values = HashMap::new();
+        values.insert("slam".to_owned(), self.slam.to_string());
+
+        storage::save(TUTORIAL_FILE, &values);
+    }
+
+    fn load() -> Self {
+        let values = storage::load(TUTORIAL_FILE);
+
+        Self {
+            slam: values.get("slam").and_then(|value| value.parse().ok()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Sent when the player starts a slam.
+pub struct PlayerSlammed;
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TutorialSeen::load())
+            .add_event::<PlayerSlammed>()
+            .add_systems((show_slam_prompt, dismiss_slam_prompt));
+    }
+}
+
+/// Suggests slamming while the player is in the air over a gap or an enemy.
+#[derive(Component)]
+struct SlamPrompt;
+
+fn show_slam_prompt(
+    mut commands: Commands,
+    seen: Res<TutorialSeen>,
+    game_state: Res<GameState>,
+    player: Query<(Entity, &Transform, &PlayerPhysics), With<Player>>,
+    enemies: Query<(), With<Enemy>>,
+    prompt: Query<Entity, With<SlamPrompt>>,
+    ui: Query<Entity, With<PlayerUi>>,
+    rapier_context: Res<RapierContext>,
+    config: Res<InputConfig>,
+    font: Res<StandardFont>,
+) {
+    if seen.slam || *game_state != GameState::Gameplay {
+        return;
+    }
+    let Ok((entity, transform, physics)) = player.get_single() else { return };
+
+    let below = if physics.grounded {
+        None
+    } else {
+        let filter = QueryFilter::new()
+            .exclude_collider(entity)
+            .exclude_sensors()
+            .groups(CollisionGroups::new(Group::GROUP_2, Group::GROUP_1 | Group::GROUP_4));
+
+        Some(rapier_context.cast_ray(
+            transform.translation.truncate(),
+            Vec2::NEG_Y,
+            GAP_DEPTH,
+            true,
+            filter,
+        ))
+    };
+
+    let suggest = match below {
+        // Nothing to land on
+        Some(None) => true,
+        Some(Some((hit, _))) => enemies.contains(hit),
+        None => false,
+    };
+
+    match (suggest, prompt.get_single()) {
+        (true, Err(_)) => {
+            let Ok(ui) = ui.get_single() else { return };
+            let key = config
+                .key(Action::Crouch)
+                .map_or("Crouch".to_owned(), |key| format!("{:?}", key));
+
+            commands.entity(ui).with_children(|parent| {
+                parent.spawn((
+                    SlamPrompt,
+                    Text2dBundle {
+                        text: Text::from_section(
+                            format!("[Press {} in the Air to Slam]", key),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 16.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., -72., layers::HUD_TEXT),
+                        ..default()
+                    },
+                ));
+            });
+        }
+        // Landing without slamming hides the prompt until the next chance
+        (false, Ok(prompt)) if physics.grounded => {
+            commands.entity(prompt).despawn();
+        }
+        _ => {}
+    }
+}
+
+fn dismiss_slam_prompt(
+    mut commands: Commands,
+    mut slammed: EventReader<PlayerSlammed>,
+    mut seen: ResMut<TutorialSeen>,
+    prompt: Query<Entity, With<SlamPrompt>>,
+) {
+    if slammed.iter().count() == 0 || seen.slam {
+        return;
+    }
+
+    seen.slam = true;
+    seen.save();
+
+    for prompt in prompt.iter() {
+        commands.entity(prompt).despawn();
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(feature = "native")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+#[cfg(feature = "browser")]
+use stdweb::web::Date;
+
+/// A small, seedable random number generator (xorshift64*), so gameplay randomness can be reproduced.
+#[derive(Resource)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        #[cfg(feature = "native")]
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        #[cfg(feature = "browser")]
+        let seed = Date::now() as u64;
+
+        Self::new(seed)
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Self {
+            state: seed ^ 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns true with the given probability.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    enemies::DamageGiven,
+    player::DamageTaken,
+    storage,
+    unlocks::PracticeRun,
+    world::StandardFont,
+    GameMode, GameState, GameTimer,
+};
+
+const SAVE_FILE: &str = "high_scores";
+/// How many scores the high-score table keeps.
+const TABLE_SIZE: usize = 5;
+
+pub const ENEMY_POINTS: i32 = 100;
+pub const BOSS_POINTS: i32 = 1000;
+pub const PICKUP_POINTS: i32 = 25;
+pub const HEART_POINTS: i32 = 1000;
+/// Points for each second left on the clock when the gold heart is collected.
+const POINTS_PER_SECOND: f32 = 10.;
+/// What the score is multiplied by for each achievement kept until the end of the run.
+const ACHIEVEMENT_MULTIPLIER: f32 = 2.;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ScoreEvent>()
+            .insert_resource(Score::default())
+            .insert_resource(HighScores::load())
+            .add_system(accumulate_score)
+            .add_system(finish_score.after(accumulate_score))
+            .add_system(update_score_ui.after(accumulate_score));
+    }
+}
+
+/// Points earned so far this run.
+#[derive(Resource, Default)]
+pub struct Score(pub i32);
+
+/// Sent to add points to the [`Score`].
+pub struct ScoreEvent(pub i32);
+
+/// Points for the time left on the clock, given when the game is won.
+pub fn time_points(timer: &GameTimer) -> i32 {
+    (timer.remaining_secs().max(0.) * POINTS_PER_SECOND) as i32
+}
+
+pub fn accumulate_score(mut events: EventReader<ScoreEvent>, mut score: ResMut<Score>) {
+    for ScoreEvent(points) in events.iter() {
+        score.0 += points;
+    }
+}
+
+/// The best campaign scores, kept between sessions.
+#[derive(Resource, Default)]
+pub struct HighScores {
+    /// Highest first.
+    pub scores: Vec<i32>,
+    /// Where the run that just ended placed in the table, for the end screens to point out.
+    pub new_rank: Option<usize>,
+}
+
+impl HighScores {
+    fn save(&self) {
+        let values: HashMap<String, String> = self
+            .scores
+            .iter()
+            .enumerate()
+            .map(|(i, score)| ((i + 1).to_string(), score.to_string()))
+            .collect();
+
+        storage::save(SAVE_FILE, &values);
+    }
+
+    /// Loads the table, skipping any scores that can't be read.
+    fn load() -> Self {
+        let values = storage::load(SAVE_FILE);
+
+        let mut scores: Vec<i32> = (1..=TABLE_SIZE)
+            .filter_map(|rank| values.get(&rank.to_string())?.parse().ok())
+            .collect();
+        scores.sort_unstable_by(|a, b| b.cmp(a));
+
+        Self {
+            scores,
+            new_rank: None,
+        }
+    }
+
+    /// Adds a score if it makes the table, returning where it placed.
+    fn record(&mut self, score: i32) -> Option<usize> {
+        let rank = self.scores.iter().position(|other| score > *other).unwrap_or(self.scores.len());
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+
+        self.scores.insert(rank, score);
+        self.scores.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+}
+
+/// Applies the achievement multipliers once the game is won, and records the score when the run ends.
+pub fn finish_score(
+    game_state: Res<GameState>,
+    mode: Res<GameMode>,
+    practice: Res<PracticeRun>,
+    mut score: ResMut<Score>,
+    mut high_scores: ResMut<HighScores>,
+    damage_given: Res<DamageGiven>,
+    damage_taken: Res<DamageTaken>,
+) {
+    if !game_state.is_changed()
+        || !matches!(*game_state, GameState::WinScreen | GameState::GameOver)
+    {
+        return;
+    }
+    high_scores.new_rank = None;
+
+    // The achievements only count for beating the game, like on the win screen
+    if *game_state == GameState::WinScreen {
+        let mut multiplier = 1.;
+        if !damage_taken.0 {
+            multiplier *= ACHIEVEMENT_MULTIPLIER;
+        }
+        if !damage_given.0 {
+            multiplier *= ACHIEVEMENT_MULTIPLIER;
+        }
+        score.0 = (score.0 as f32 * multiplier) as i32;
+    }
+
+    // Like the best time, only whole campaign runs go in the table
+    if *mode != GameMode::Campaign || practice.0 || score.0 <= 0 {
+        return;
+    }
+
+    high_scores.new_rank = high_scores.record(score.0);
+    if high_scores.new_rank.is_some() {
+        high_scores.save();
+    }
+}
+
+/// Shows the final score at `y`, with the high-score table underneath it in campaign runs.
+pub fn spawn_score_summary(
+    parent: &mut ChildBuilder,
+    font: &StandardFont,
+    score: &Score,
+    high_scores: Option<&HighScores>,
+    color: Color,
+    y: f32,
+) {
+    let score_text = match high_scores.and_then(|high_scores| high_scores.new_rank) {
+        Some(0) => format!("Score: {} (New High Score!)", score.0),
+        Some(rank) => format!("Score: {} (#{} High Score!)", score.0, rank + 1),
+        None => format!("Score: {}", score.0),
+    };
+
+    parent.spawn(Text2dBundle {
+        text: Text::from_section(
+            score_text,
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 20.0,
+                color,
+            },
+        )
+        .with_alignment(TextAlignment::Center),
+        transform: Transform::from_xyz(0., y, 0.),
+        ..default()
+    });
+
+    let Some(high_scores) = high_scores.filter(|high_scores| !high_scores.scores.is_empty()) else { return };
+
+    let table: Vec<String> = high_scores.scores.iter().map(|score| score.to_string()).collect();
+
+    parent.spawn(Text2dBundle {
+        text: Text::from_section(
+            format!("High Scores: {}", table.join("  ")),
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 16.0,
+                color,
+            },
+        )
+        .with_alignment(TextAlignment::Center),
+        transform: Transform::from_xyz(0., y - 20., 0.),
+        ..default()
+    });
+}
+
+/// The running score in the gameplay HUD, beside the timer.
+#[derive(Component)]
+pub struct ScoreText;
+
+fn update_score_ui(
+    mut text: Query<&mut Text, With<ScoreText>>,
+    score: Res<Score>,
+    font: Res<StandardFont>,
+) {
+    let Ok(mut text) = text.get_single_mut() else { return };
+    if !score.is_changed() && !text.sections.is_empty() {
+        return;
+    }
+
+    *text = Text::from_section(
+        format!("Score: {}", score.0),
+        TextStyle {
+            font: font.0.clone(),
+            font_size: 16.0,
+            color: Color::WHITE,
+        },
+    )
+    .with_alignment(TextAlignment::Center);
+}
@@ -0,0 +1,549 @@
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+
+use crate::{
+    animator::{HighContrast, ParticleQuality, YSortEnabled},
+    enemies::Difficulty,
+    player::{
+        abilities::{Abilities, ChaosPotions, KeyboardAim, StartingAbility},
+        controls::{Action, ControlPreset, InputConfig},
+        Brightness, CameraZoom, DoubleJumpEnabled, HudLayout, PlayerSkin, ScreenShakeEnabled,
+        ShowHud,
+    },
+    storage,
+    unlocks::{Cloak, Unlocks},
+    world::{ShowCollisions, StandardFont},
+    GameState,
+};
+
+pub struct SettingsPlugin;
+
+/// A setting with a fixed list of options, stepped through in the menu and saved by name.
+pub trait Choice: Copy + PartialEq + 'static {
+    const ALL: &'static [Self];
+
+    fn name(&self) -> &'static str;
+
+    /// Moves `delta` options along, wrapping around at either end.
+    fn step(&mut self, delta: i32) {
+        let index = Self::ALL.iter().position(|option| option == self).unwrap_or_default() as i32;
+        *self = Self::ALL[(index + delta).rem_euclid(Self::ALL.len() as i32) as usize];
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|option| option.name() == name)
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SelectedSetting(0))
+            .add_startup_system(load_settings)
+            .add_systems((
+            spawn_settings_menu,
+            settings_menu,
+            update_settings_menu.after(settings_menu),
+            despawn_settings_menu,
+        ));
+
+        app.insert_resource(ControlsMenuState::default()).add_systems((
+            spawn_controls_menu,
+            controls_menu,
+            update_controls_menu.after(controls_menu),
+            despawn_controls_menu,
+        ));
+    }
+}
+
+/// Every player-facing setting, so the menu can list and change them in one place.
+#[derive(SystemParam)]
+pub struct Settings<'w, 's> {
+    pub zoom: ResMut<'w, CameraZoom>,
+    pub show_collisions: ResMut<'w, ShowCollisions>,
+    pub brightness: ResMut<'w, Brightness>,
+    pub high_contrast: ResMut<'w, HighContrast>,
+    pub chaos_potions: ResMut<'w, ChaosPotions>,
+    pub screen_shake: ResMut<'w, ScreenShakeEnabled>,
+    pub particle_quality: ResMut<'w, ParticleQuality>,
+    pub double_jump: ResMut<'w, DoubleJumpEnabled>,
+    pub y_sort: ResMut<'w, YSortEnabled>,
+    pub difficulty: ResMut<'w, Difficulty>,
+    pub starting_ability: ResMut<'w, StartingAbility>,
+    pub keyboard_aim: ResMut<'w, KeyboardAim>,
+    pub input_config: ResMut<'w, InputConfig>,
+    pub appearance: Appearance<'w, 's>,
+    abilities: Res<'w, Abilities>,
+    marker: PhantomData<&'s ()>,
+}
+
+/// Settings for how the HUD and the player look.
+/// Split out of [`Settings`], since a system param can only hold sixteen others.
+#[derive(SystemParam)]
+pub struct Appearance<'w, 's> {
+    pub show_hud: ResMut<'w, ShowHud>,
+    pub hud_layout: ResMut<'w, HudLayout>,
+    pub cloak: ResMut<'w, Cloak>,
+    pub player_skin: ResMut<'w, PlayerSkin>,
+    unlocks: Res<'w, Unlocks>,
+    marker: PhantomData<&'s ()>,
+}
+
+impl Settings<'_, '_> {
+    fn labels(&self) -> Vec<String> {
+        vec![
+            format!("Zoom: {:.1}x", self.zoom.0),
+            format!("Show Collisions: {}", on_off(self.show_collisions.0)),
+            format!("Brightness: {:.0}%", self.brightness.0 * 100.),
+            format!("High Contrast: {}", on_off(self.high_contrast.0)),
+            format!("Chaos Potions: {}", on_off(self.chaos_potions.0)),
+            format!("Screen Shake: {}", on_off(self.screen_shake.0)),
+            format!("Particles: {}", self.particle_quality.name()),
+            format!("Double Jump: {}", on_off(self.double_jump.0)),
+            format!("Y-Sorting: {}", on_off(self.y_sort.0)),
+            format!("Difficulty: {}", self.difficulty.name()),
+            format!(
+                "Starting Potion: {}",
+                self.abilities.get(self.starting_ability.0).map_or("None", |ability| ability.name)
+            ),
+            format!("HUD: {}", on_off(self.appearance.show_hud.0)),
+            format!("HUD Layout: {}", self.appearance.hud_layout.name()),
+            format!("Keyboard Aim: {}", on_off(self.keyboard_aim.enabled)),
+            format!(
+                "Controls: {}",
+                ControlPreset::matching(&self.input_config).map_or("Custom", |preset| preset.name())
+            ),
+            format!("Cloak: {}", self.appearance.cloak.name()),
+        ]
+    }
+
+    fn change(&mut self, index: usize, delta: i32) {
+        match index {
+            0 => self.zoom.step(delta),
+            1 => self.show_collisions.0 = !self.show_collisions.0,
+            2 => self.brightness.step(delta),
+            3 => self.high_contrast.0 = !self.high_contrast.0,
+            4 => self.chaos_potions.0 = !self.chaos_potions.0,
+            5 => self.screen_shake.0 = !self.screen_shake.0,
+            6 => self.particle_quality.step(delta),
+            7 => self.double_jump.0 = !self.double_jump.0,
+            8 => self.y_sort.0 = !self.y_sort.0,
+            9 => self.difficulty.step(delta),
+            10 => self.starting_ability.step(delta, self.abilities.len()),
+            11 => self.appearance.show_hud.0 = !self.appearance.show_hud.0,
+            12 => self.appearance.hud_layout.toggle(),
+            13 => self.keyboard_aim.enabled = !self.keyboard_aim.enabled,
+            14 => {
+                // Custom bindings step onto the first preset
+                let preset = match ControlPreset::matching(&self.input_config) {
+                    Some(mut preset) => {
+                        preset.step(delta);
+                        preset
+                    }
+                    None => ControlPreset::Default,
+                };
+                *self.input_config = preset.bindings();
+            }
+            15 => self.appearance.cloak.step(delta, &self.appearance.unlocks),
+            _ => {}
+        }
+    }
+
+    /// Saves the settings that should carry over between sessions.
+    /// Debug settings like collision rendering aren't saved.
+    fn save(&self) {
+        let mut values = HashMap::new();
+        values.insert("zoom".to_owned(), self.zoom.0.to_string());
+        values.insert("brightness".to_owned(), self.brightness.0.to_string());
+        values.insert("high_contrast".to_owned(), self.high_contrast.0.to_string());
+        values.insert("chaos_potions".to_owned(), self.chaos_potions.0.to_string());
+        values.insert("screen_shake".to_owned(), self.screen_shake.0.to_string());
+        values.insert("particle_quality".to_owned(), self.particle_quality.name().to_owned());
+        values.insert("double_jump".to_owned(), self.double_jump.0.to_string());
+        values.insert("y_sort".to_owned(), self.y_sort.0.to_string());
+        values.insert("difficulty".to_owned(), self.difficulty.name().to_owned());
+        values.insert("show_hud".to_owned(), self.appearance.show_hud.0.to_string());
+        values.insert("hud_layout".to_owned(), self.appearance.hud_layout.name().to_owned());
+        values.insert("keyboard_aim".to_owned(), self.keyboard_aim.enabled.to_string());
+        // Individual rebinds aren't saved, only which preset is in use
+        if let Some(preset) = ControlPreset::matching(&self.input_config) {
+            values.insert("controls".to_owned(), preset.name().to_owned());
+        }
+        values.insert("cloak".to_owned(), self.appearance.cloak.name().to_owned());
+        values.insert("player_skin".to_owned(), self.appearance.player_skin.path.clone());
+        if let Some(ability) = self.abilities.get(self.starting_ability.0) {
+            values.insert("starting_ability".to_owned(), ability.name.to_owned());
+        }
+
+        storage::save(SETTINGS_FILE, &values);
+    }
+
+    fn load(&mut self) {
+        let values = storage::load(SETTINGS_FILE);
+        let get = |key: &str| values.get(key).map(String::as_str);
+
+        if let Some(zoom) = get("zoom").and_then(|value| value.parse().ok()) {
+            self.zoom.0 = f32::clamp(zoom, CameraZoom::MIN, CameraZoom::MAX);
+        }
+        if let Some(brightness) = get("brightness").and_then(|value| value.parse().ok()) {
+            self.brightness.0 = f32::clamp(brightness, Brightness::MIN, Brightness::MAX);
+        }
+        if let Some(high_contrast) = get("high_contrast").and_then(|value| value.parse().ok()) {
+            self.high_contrast.0 = high_contrast;
+        }
+        if let Some(chaos_potions) = get("chaos_potions").and_then(|value| value.parse().ok()) {
+            self.chaos_potions.0 = chaos_potions;
+        }
+        if let Some(screen_shake) = get("screen_shake").and_then(|value| value.parse().ok()) {
+            self.screen_shake.0 = screen_shake;
+        }
+        if let Some(particle_quality) = get("particle_quality").and_then(ParticleQuality::from_name) {
+            *self.particle_quality = particle_quality;
+        }
+        if let Some(double_jump) = get("double_jump").and_then(|value| value.parse().ok()) {
+            self.double_jump.0 = double_jump;
+        }
+        if let Some(y_sort) = get("y_sort").and_then(|value| value.parse().ok()) {
+            self.y_sort.0 = y_sort;
+        }
+        if let Some(difficulty) = get("difficulty").and_then(Difficulty::from_name) {
+            *self.difficulty = difficulty;
+        }
+        if let Some(show_hud) = get("show_hud").and_then(|value| value.parse().ok()) {
+            self.appearance.show_hud.0 = show_hud;
+        }
+        if let Some(hud_layout) = get("hud_layout").and_then(HudLayout::from_name) {
+            *self.appearance.hud_layout = hud_layout;
+        }
+        if let Some(keyboard_aim) = get("keyboard_aim").and_then(|value| value.parse().ok()) {
+            self.keyboard_aim.enabled = keyboard_aim;
+        }
+        if let Some(preset) = get("controls").and_then(ControlPreset::from_name) {
+            *self.input_config = preset.bindings();
+        }
+        // A cloak that's no longer unlocked, like after the unlocks were cleared, falls back to the default
+        if let Some(cloak) = get("cloak")
+            .and_then(Cloak::from_name)
+            .filter(|cloak| self.appearance.unlocks.has(*cloak))
+        {
+            *self.appearance.cloak = cloak;
+        }
+        // There's no menu entry for this, it's for pointing at custom art by editing the settings file
+        if let Some(path) = get("player_skin") {
+            self.appearance.player_skin.path = path.to_owned();
+        }
+        // Saved by name, so it survives abilities being reordered
+        if let Some(index) = get("starting_ability").and_then(|name| self.abilities.index_by_name(name)) {
+            self.starting_ability.0 = index;
+        }
+    }
+}
+
+const SETTINGS_FILE: &str = "settings";
+
+fn load_settings(mut settings: Settings) {
+    settings.load();
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+#[derive(Resource)]
+struct SelectedSetting(usize);
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct SettingsEntry(usize);
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    font: Res<StandardFont>,
+    settings: Settings,
+) {
+    if !game_state.is_changed() || *game_state != GameState::Settings {
+        return;
+    }
+
+    commands
+        .spawn(SettingsMenu)
+        .insert(SpatialBundle::default())
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "Settings",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 50.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., 132.0, 0.),
+                ..default()
+            });
+
+            for (i, label) in settings.labels().into_iter().enumerate() {
+                parent.spawn((
+                    SettingsEntry(i),
+                    Text2dBundle {
+                        text: Text::from_section(
+                            label,
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 16.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., 104.0 - 14.0 * i as f32, 0.),
+                        ..default()
+                    },
+                ));
+            }
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[W/S to Select, A/D to Change, Esc to Return]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., -128.0, 0.),
+                ..default()
+            });
+        });
+}
+
+fn settings_menu(
+    mut game_state: ResMut<GameState>,
+    keys: Res<Input<KeyCode>>,
+    mut selected: ResMut<SelectedSetting>,
+    mut settings: Settings,
+) {
+    if *game_state != GameState::Settings {
+        return;
+    }
+
+    let count = settings.labels().len();
+
+    if keys.just_pressed(KeyCode::W) {
+        selected.0 = (selected.0 + count - 1) % count;
+    }
+    if keys.just_pressed(KeyCode::S) {
+        selected.0 = (selected.0 + 1) % count;
+    }
+    if keys.just_pressed(KeyCode::A) {
+        settings.change(selected.0, -1);
+    }
+    if keys.just_pressed(KeyCode::D) {
+        settings.change(selected.0, 1);
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        settings.save();
+        *game_state = GameState::StartMenu;
+    }
+}
+
+fn update_settings_menu(
+    game_state: Res<GameState>,
+    selected: Res<SelectedSetting>,
+    settings: Settings,
+    mut entries: Query<(&SettingsEntry, &mut Text)>,
+) {
+    if *game_state != GameState::Settings {
+        return;
+    }
+
+    let labels = settings.labels();
+
+    for (entry, mut text) in entries.iter_mut() {
+        let section = &mut text.sections[0];
+        section.value = labels[entry.0].clone();
+        section.style.color = if entry.0 == selected.0 {
+            Color::GOLD
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn despawn_settings_menu(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    settings_menu: Query<Entity, With<SettingsMenu>>,
+) {
+    if game_state.is_changed() && *game_state != GameState::Settings {
+        for settings_menu in settings_menu.iter() {
+            commands.entity(settings_menu).despawn_recursive();
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ControlsMenuState {
+    selected: usize,
+    /// Whether the next key pressed is bound to the selected action.
+    rebinding: bool,
+}
+
+#[derive(Component)]
+struct ControlsMenu;
+
+#[derive(Component)]
+struct ControlsEntry(usize);
+
+fn control_label(config: &InputConfig, action: Action, rebinding: bool) -> String {
+    if rebinding {
+        format!("{}: [Press a Key]", action.name())
+    } else {
+        match config.key(action) {
+            Some(key) => format!("{}: {:?}", action.name(), key),
+            None => format!("{}: Unbound", action.name()),
+        }
+    }
+}
+
+fn spawn_controls_menu(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    font: Res<StandardFont>,
+    config: Res<InputConfig>,
+) {
+    if !game_state.is_changed() || *game_state != GameState::Controls {
+        return;
+    }
+
+    commands
+        .spawn(ControlsMenu)
+        .insert(SpatialBundle::default())
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "Controls",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 50.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., 112.0, 0.),
+                ..default()
+            });
+
+            for (i, action) in Action::ALL.into_iter().enumerate() {
+                parent.spawn((
+                    ControlsEntry(i),
+                    Text2dBundle {
+                        text: Text::from_section(
+                            control_label(&config, action, false),
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: 16.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0., 80.0 - 17.0 * i as f32, 0.),
+                        ..default()
+                    },
+                ));
+            }
+
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    "[Up/Down to Select, Enter to Rebind, Esc to Return]",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_xyz(0., -128.0, 0.),
+                ..default()
+            });
+        });
+}
+
+fn controls_menu(
+    mut game_state: ResMut<GameState>,
+    keys: Res<Input<KeyCode>>,
+    mut menu: ResMut<ControlsMenuState>,
+    mut config: ResMut<InputConfig>,
+) {
+    if *game_state != GameState::Controls {
+        menu.rebinding = false;
+        return;
+    }
+
+    if menu.rebinding {
+        // Escape backs out of rebinding, so it can't be bound itself
+        if keys.just_pressed(KeyCode::Escape) {
+            menu.rebinding = false;
+        } else if let Some(key) = keys.get_just_pressed().next() {
+            config.bind(Action::ALL[menu.selected], *key);
+            menu.rebinding = false;
+        }
+        return;
+    }
+
+    // The menu uses fixed keys, so it can't be locked out by a bad binding
+    let count = Action::ALL.len();
+
+    if keys.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + count - 1) % count;
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % count;
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        menu.rebinding = true;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        *game_state = GameState::StartMenu;
+    }
+}
+
+fn update_controls_menu(
+    game_state: Res<GameState>,
+    menu: Res<ControlsMenuState>,
+    config: Res<InputConfig>,
+    mut entries: Query<(&ControlsEntry, &mut Text)>,
+) {
+    if *game_state != GameState::Controls {
+        return;
+    }
+
+    for (entry, mut text) in entries.iter_mut() {
+        let selected = entry.0 == menu.selected;
+        let section = &mut text.sections[0];
+        section.value = control_label(&config, Action::ALL[entry.0], selected && menu.rebinding);
+        section.style.color = if selected { Color::GOLD } else { Color::WHITE };
+    }
+}
+
+fn despawn_controls_menu(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    controls_menu: Query<Entity, With<ControlsMenu>>,
+) {
+    if game_state.is_changed() && *game_state != GameState::Controls {
+        for controls_menu in controls_menu.iter() {
+            commands.entity(controls_menu).despawn_recursive();
+        }
+    }
+}
@@ -1,16 +1,134 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::{Duration, HashMap}};
+use bevy_ecs_ldtk::{ldtk::FieldValue, prelude::*};
 use bevy_kira_audio::prelude::*;
 
+use crate::{enemies::Boss, world::field_value};
+
 pub struct SoundPlugin;
 
 impl Plugin for SoundPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(AudioPlugin).add_startup_system(start_music);
+        let asset_server = app.world.resource::<AssetServer>();
+        let default = asset_server.load("audio/PotionPanic.wav");
+
+        app.add_plugin(AudioPlugin)
+            .insert_resource(LevelMusic {
+                default,
+                tracks: HashMap::new(),
+            })
+            .add_startup_system(start_music)
+            .add_system(load_level_music)
+            .add_system(update_music.after(load_level_music));
+
+        #[cfg(debug_assertions)]
+        app.insert_resource(MusicAudition::default());
+    }
+}
+
+const CROSSFADE: Duration = Duration::from_secs(1);
+
+/// Music for each level, keyed by level index.
+/// Levels choose a track with a "Music" string field in LDtk.
+#[derive(Resource)]
+pub struct LevelMusic {
+    pub default: Handle<AudioSource>,
+    pub tracks: HashMap<usize, Handle<AudioSource>>,
+}
+
+impl LevelMusic {
+    pub fn track(&self, level: usize) -> &Handle<AudioSource> {
+        self.tracks.get(&level).unwrap_or(&self.default)
+    }
+}
+
+/// A track picked with the debug keys, played instead of the level's music so it can be heard anywhere.
+#[cfg(debug_assertions)]
+#[derive(Resource, Default)]
+pub struct MusicAudition(pub Option<Handle<AudioSource>>);
+
+/// The music that is currently playing.
+#[derive(Resource)]
+pub struct Music {
+    pub track: Handle<AudioSource>,
+    pub instance: Handle<AudioInstance>,
+}
+
+impl Music {
+    /// Fades out the current track and fades in the new one, unless it's already playing.
+    pub fn crossfade(
+        &mut self,
+        track: &Handle<AudioSource>,
+        audio: &Audio,
+        instances: &mut Assets<AudioInstance>,
+    ) {
+        if self.track == *track {
+            return;
+        }
+
+        if let Some(instance) = instances.get_mut(&self.instance) {
+            instance.stop(AudioTween::linear(CROSSFADE));
+        }
+
+        self.track = track.clone();
+        self.instance = audio
+            .play(track.clone())
+            .looped()
+            .fade_in(AudioTween::linear(CROSSFADE))
+            .handle();
     }
 }
 
-fn start_music(asset_server: Res<AssetServer>, audio: Res<Audio>) {
-    audio
-        .play(asset_server.load("audio/PotionPanic.wav"))
-        .looped();
+fn start_music(mut commands: Commands, level_music: Res<LevelMusic>, audio: Res<Audio>) {
+    let track = level_music.default.clone();
+    let instance = audio.play(track.clone()).looped().handle();
+
+    commands.insert_resource(Music { track, instance });
+}
+
+fn load_level_music(
+    mut level_music: ResMut<LevelMusic>,
+    mut ldtk_events: EventReader<AssetEvent<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in ldtk_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else { continue };
+        let Some(ldtk) = ldtk_assets.get(handle) else { continue };
+
+        level_music.tracks.clear();
+
+        for (i, level) in ldtk.iter_levels().enumerate() {
+            if let Some(FieldValue::String(Some(path))) = field_value(&level.field_instances, "Music") {
+                level_music.tracks.insert(i, asset_server.load(path.as_str()));
+            }
+        }
+    }
+}
+
+/// Plays the boss's music while a boss is alive, otherwise the current level's music.
+fn update_music(
+    level_selection: Res<LevelSelection>,
+    level_music: Res<LevelMusic>,
+    music: Option<ResMut<Music>>,
+    bosses: Query<&Boss>,
+    audio: Res<Audio>,
+    mut instances: ResMut<Assets<AudioInstance>>,
+    #[cfg(debug_assertions)] audition: Res<MusicAudition>,
+) {
+    let Some(mut music) = music else { return };
+
+    #[cfg(debug_assertions)]
+    if let Some(track) = &audition.0 {
+        music.crossfade(track, &audio, &mut instances);
+        return;
+    }
+
+    let LevelSelection::Index(level) = *level_selection else { return };
+
+    let track = bosses
+        .iter()
+        .find_map(|boss| boss.music.as_ref())
+        .unwrap_or_else(|| level_music.track(level));
+
+    music.crossfade(track, &audio, &mut instances);
 }
@@ -0,0 +1,75 @@
+#[cfg(feature = "native")]
+use std::path::PathBuf;
+
+use bevy::{prelude::*, utils::HashMap};
+
+/// Loads the values saved under `name`, or nothing if they haven't been saved before.
+pub fn load(name: &str) -> HashMap<String, String> {
+    read(name).map(|text| parse(&text)).unwrap_or_default()
+}
+
+/// Saves `values` under `name`, replacing whatever was saved there before.
+pub fn save(name: &str, values: &HashMap<String, String>) {
+    let mut text = String::new();
+    for (key, value) in values {
+        text.push_str(&format!("{key}={value}\n"));
+    }
+
+    write(name, &text);
+}
+
+pub fn parse(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
+
+/// Where saves go on native, following each platform's convention for app data.
+#[cfg(feature = "native")]
+fn path(name: &str) -> PathBuf {
+    let base = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_DATA_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default();
+
+    base.join("potion_panic").join(format!("{name}.txt"))
+}
+
+#[cfg(feature = "native")]
+fn read(name: &str) -> Option<String> {
+    std::fs::read_to_string(path(name)).ok()
+}
+
+#[cfg(feature = "native")]
+fn write(name: &str, text: &str) {
+    let path = path(name);
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, text));
+
+    if let Err(err) = result {
+        warn!("Failed to save {}: {err}", path.display());
+    }
+}
+
+#[cfg(feature = "browser")]
+fn read(name: &str) -> Option<String> {
+    stdweb::web::window()
+        .local_storage()
+        .get(&format!("potion_panic.{name}"))
+}
+
+#[cfg(feature = "browser")]
+fn write(name: &str, text: &str) {
+    if stdweb::web::window()
+        .local_storage()
+        .insert(&format!("potion_panic.{name}"), text)
+        .is_err()
+    {
+        warn!("Failed to save {name}");
+    }
+}
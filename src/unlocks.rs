@@ -0,0 +1,179 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    enemies::DamageGiven,
+    player::DamageTaken,
+    storage, GameMode, GameState,
+};
+
+const SAVE_FILE: &str = "unlocks";
+
+pub struct UnlocksPlugin;
+
+impl Plugin for UnlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Unlocks::load())
+            .insert_resource(Cloak::default())
+            .insert_resource(NewUnlocks::default())
+            .insert_resource(PracticeRun(false))
+            .add_system(unlock_on_win);
+    }
+}
+
+/// What the player has earned by beating the game, kept between sessions.
+#[derive(Resource, Default)]
+pub struct Unlocks {
+    /// Beat the game.
+    pub completed: bool,
+    /// Beat the game without taking damage.
+    pub flawless: bool,
+    /// Beat the game without hurting an enemy.
+    pub pacifist: bool,
+}
+
+impl Unlocks {
+    fn save(&self) {
+        let mut values = HashMap::new();
+        values.insert("completed".to_owned(), self.completed.to_string());
+        values.insert("flawless".to_owned(), self.flawless.to_string());
+        values.insert("pacifist".to_owned(), self.pacifist.to_string());
+
+        storage::save(SAVE_FILE, &values);
+    }
+
+    fn load() -> Self {
+        let values = storage::load(SAVE_FILE);
+        let get = |key: &str| values.get(key).and_then(|value| value.parse().ok()).unwrap_or_default();
+
+        Self {
+            completed: get("completed"),
+            flawless: get("flawless"),
+            pacifist: get("pacifist"),
+        }
+    }
+
+    pub fn has(&self, cloak: Cloak) -> bool {
+        match cloak {
+            Cloak::Default | Cloak::Crimson | Cloak::Violet | Cloak::Ash => true,
+            Cloak::Gold => self.completed,
+            Cloak::Azure => self.flawless,
+            Cloak::Verdant => self.pacifist,
+        }
+    }
+}
+
+/// A tint for the player's cloak, picked in the settings.
+/// The first few are always available, and the rest come from [`Unlocks`].
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Cloak {
+    #[default]
+    Default,
+    Crimson,
+    Violet,
+    Ash,
+    Gold,
+    Azure,
+    Verdant,
+}
+
+impl Cloak {
+    pub const ALL: [Self; 7] = [
+        Self::Default,
+        Self::Crimson,
+        Self::Violet,
+        Self::Ash,
+        Self::Gold,
+        Self::Azure,
+        Self::Verdant,
+    ];
+
+    /// Moves to the next unlocked cloak, skipping any that are still locked.
+    pub fn step(&mut self, delta: i32, unlocks: &Unlocks) {
+        let unlocked: Vec<Self> = Self::ALL.into_iter().filter(|cloak| unlocks.has(*cloak)).collect();
+        let index = unlocked.iter().position(|cloak| cloak == self).unwrap_or_default() as i32;
+        *self = unlocked[(index + delta).rem_euclid(unlocked.len() as i32) as usize];
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Default => Color::WHITE,
+            Self::Crimson => Color::rgb(1., 0.6, 0.6),
+            Self::Violet => Color::rgb(0.8, 0.65, 1.),
+            Self::Ash => Color::rgb(0.7, 0.7, 0.7),
+            Self::Gold => Color::rgb(1., 0.85, 0.4),
+            Self::Azure => Color::rgb(0.55, 0.75, 1.),
+            Self::Verdant => Color::rgb(0.6, 1., 0.6),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Crimson => "Crimson",
+            Self::Violet => "Violet",
+            Self::Ash => "Ash",
+            Self::Gold => "Gold",
+            Self::Azure => "Azure",
+            Self::Verdant => "Verdant",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cloak| cloak.name() == name)
+    }
+
+    /// What earns the cloak, for showing on the win screen.
+    pub fn requirement(&self) -> &'static str {
+        match self {
+            Self::Default | Self::Crimson | Self::Violet | Self::Ash => "",
+            Self::Gold => "Beat the game",
+            Self::Azure => "Beat the game without taking damage",
+            Self::Verdant => "Beat the game without hurting enemies",
+        }
+    }
+}
+
+/// Cloaks unlocked by the run that just ended, so the win screen can announce them.
+#[derive(Resource, Default)]
+pub struct NewUnlocks(pub Vec<Cloak>);
+
+/// Whether a level has been restarted this run, which stops the run from earning unlocks.
+#[derive(Resource)]
+pub struct PracticeRun(pub bool);
+
+pub fn unlock_on_win(
+    game_state: Res<GameState>,
+    mode: Res<GameMode>,
+    practice: Res<PracticeRun>,
+    mut unlocks: ResMut<Unlocks>,
+    mut new_unlocks: ResMut<NewUnlocks>,
+    damage_given: Res<DamageGiven>,
+    damage_taken: Res<DamageTaken>,
+) {
+    if !game_state.is_changed() || *game_state != GameState::WinScreen {
+        return;
+    }
+    new_unlocks.0.clear();
+
+    // Only finishing the campaign in one go counts as beating the game
+    if *mode != GameMode::Campaign || practice.0 {
+        return;
+    }
+
+    let before: Vec<bool> = Cloak::ALL.iter().map(|cloak| unlocks.has(*cloak)).collect();
+
+    unlocks.completed = true;
+    unlocks.flawless |= !damage_taken.0;
+    unlocks.pacifist |= !damage_given.0;
+
+    new_unlocks.0 = Cloak::ALL
+        .into_iter()
+        .zip(before)
+        .filter(|(cloak, had)| !had && unlocks.has(*cloak))
+        .map(|(cloak, _)| cloak)
+        .collect();
+
+    if !new_unlocks.0.is_empty() {
+        unlocks.save();
+    }
+}
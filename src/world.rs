@@ -1,12 +1,19 @@
 use bevy::{
+    asset::LoadState,
     ecs::system::SystemParam,
     prelude::*,
+    render::texture::DEFAULT_IMAGE_HANDLE,
     utils::{HashMap, HashSet},
 };
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{ldtk::{FieldInstance, FieldValue}, prelude::*};
 use bevy_rapier2d::{prelude::*, rapier::prelude::CollisionEventFlags};
 
-use crate::{GameState, animator::{AnimationIndices, AnimationTimer}};
+use crate::{
+    ErrorMessage, GameState, GameTimer,
+    animator::{AnimationClip, AnimationStateMachine},
+    player::{Player, PlayerGroundSensor, PlayerInput, PlayerPhysics},
+    score::{self, ScoreEvent},
+};
 
 pub struct WorldPlugin;
 
@@ -25,14 +32,41 @@ impl Plugin for WorldPlugin {
             .add_plugin(RapierPhysicsPlugin::<GamePhysicsHooks>::pixels_per_meter(
                 32.0,
             ))
+            .add_plugin(RapierDebugRenderPlugin {
+                enabled: false,
+                ..default()
+            })
+            .insert_resource(ShowCollisions(false))
+            .add_system(show_collisions)
             .configure_set(LdtkSystemSet::ProcessApi.before(PhysicsSet::SyncBackend))
             .insert_resource(LevelSelection::Index(0))
+            .insert_resource(MapPath::default())
+            .insert_resource(LevelCount::default())
+            .insert_resource(WinReason::default())
+            .add_system(count_levels)
             .register_ldtk_int_cell::<WallBundle>(1)
+            .register_ldtk_int_cell::<HazardBundle>(SPIKES)
+            .register_ldtk_int_cell::<HazardBundle>(LAVA)
+            .register_ldtk_int_cell::<OneWayPlatformBundle>(ONE_WAY)
             .register_ldtk_entity::<GoldHeartBundle>("GoldHeart")
+            .register_ldtk_entity::<MovingPlatformBundle>("MovingPlatform")
+            .register_ldtk_entity::<LadderBundle>("Ladder")
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint")
+            .insert_resource(Checkpoint::default())
+            .add_system(checkpoint_checks)
+            .add_system(move_platforms)
+            .add_system(carry_riders.after(move_platforms))
             .add_system(setup_world)
+            .add_system(check_world_load)
             .add_system(spawn_wall_collision)
-            .add_system(heart_checks)
-            .add_system(despawn_world);
+            // Before the score is added up, so the win screen counts the heart
+            .add_system(heart_checks.before(score::accumulate_score))
+            .add_system(despawn_world)
+            .add_event::<RestartLevel>()
+            .add_system(restart_level)
+            .insert_resource(HitStop::new(0.06))
+            .add_system(update_hit_stop.before(freeze_physics))
+            .add_system(freeze_physics);
 
         let asset_server = app.world.resource::<AssetServer>();
 
@@ -51,34 +85,192 @@ impl Plugin for WorldPlugin {
 #[derive(Resource)]
 pub struct StandardFont(pub Handle<Font>);
 
+/// Finds the value of an LDtk field by its identifier.
+pub fn field_value<'a>(fields: &'a [FieldInstance], identifier: &str) -> Option<&'a FieldValue> {
+    fields
+        .iter()
+        .find(|field| field.identifier == identifier)
+        .map(|field| &field.value)
+}
+
 #[derive(Resource)]
 pub struct CursiveFont(pub Handle<Font>);
 
+/// Draws the outlines of every collider.
+#[derive(Resource)]
+pub struct ShowCollisions(pub bool);
+
+fn show_collisions(show: Res<ShowCollisions>, mut debug_render: ResMut<DebugRenderContext>) {
+    if show.is_changed() {
+        debug_render.enabled = show.0;
+    }
+}
+
 #[derive(Component)]
 pub struct World;
 
-fn setup_world(mut commands: Commands, asset_server: Res<AssetServer>, game_state: Res<GameState>) {
-    if game_state.is_changed() && *game_state == GameState::Gameplay {
+/// The LDtk file the levels are loaded from, relative to the assets.
+#[derive(Resource)]
+pub struct MapPath(pub String);
+
+impl Default for MapPath {
+    fn default() -> Self {
+        Self("map.ldtk".to_owned())
+    }
+}
+
+/// How many levels the map has, known once it's loaded. Until then it's zero.
+#[derive(Resource, Default)]
+pub struct LevelCount(pub usize);
+
+impl LevelCount {
+    /// Whether `level` is the last one in the map. Nothing is while the count isn't known yet.
+    pub fn is_final(&self, level: usize) -> bool {
+        self.0 > 0 && level + 1 >= self.0
+    }
+}
+
+fn count_levels(
+    mut events: EventReader<AssetEvent<LdtkAsset>>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    asset_server: Res<AssetServer>,
+    map: Res<MapPath>,
+    mut count: ResMut<LevelCount>,
+) {
+    let map_handle: Handle<LdtkAsset> = asset_server.get_handle(map.0.as_str());
+
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else { continue };
+        if *handle != map_handle {
+            continue;
+        }
+
+        if let Some(ldtk) = ldtk_assets.get(handle) {
+            count.0 = ldtk.iter_levels().count();
+        }
+    }
+}
+
+fn setup_world(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_state: Res<GameState>,
+    world: Query<(), With<World>>,
+    map: Res<MapPath>,
+) {
+    // Resuming from pause keeps the world that's already there
+    if game_state.is_changed() && *game_state == GameState::Gameplay && world.is_empty() {
         commands
             .spawn(LdtkWorldBundle {
-                ldtk_handle: asset_server.load("map.ldtk"),
+                ldtk_handle: asset_server.load(map.0.as_str()),
                 ..Default::default()
             })
             .insert(World);
     }
 }
 
+fn check_world_load(
+    world: Query<&Handle<LdtkAsset>, With<World>>,
+    asset_server: Res<AssetServer>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut game_state: ResMut<GameState>,
+    mut error: ResMut<ErrorMessage>,
+    map: Res<MapPath>,
+) {
+    if !game_state.in_run() {
+        return;
+    }
+
+    let Ok(handle) = world.get_single() else { return };
+
+    match asset_server.get_load_state(handle) {
+        LoadState::Failed => {
+            error.report(&mut game_state, format!("The map couldn't be loaded. Is {} missing?", map.0));
+        }
+        LoadState::Loaded => {
+            let has_levels = ldtk_assets
+                .get(handle)
+                .map_or(false, |ldtk| ldtk.iter_levels().next().is_some());
+
+            if !has_levels {
+                error.report(&mut game_state, "The map doesn't have any levels.");
+            }
+        }
+        _ => {}
+    }
+}
+
 fn despawn_world(
     mut commands: Commands,
     world: Query<Entity, With<World>>,
     game_state: Res<GameState>,
 ) {
-    if game_state.is_changed() && *game_state != GameState::Gameplay {
+    if game_state.is_changed() && !game_state.in_run() {
         let Ok(world) = world.get_single() else { return };
         commands.entity(world).despawn_recursive();
     }
 }
 
+/// Reloads the current level's entities in place, putting the player back at its start.
+/// Unlike a state change, this keeps the rest of the world and the run as they are.
+pub struct RestartLevel;
+
+fn restart_level(
+    mut commands: Commands,
+    mut restart: EventReader<RestartLevel>,
+    levels: Query<Entity, With<Handle<LdtkLevel>>>,
+) {
+    if restart.iter().count() == 0 {
+        return;
+    }
+
+    // Walls are children of their level, so they get rebuilt along with everything else
+    for level in levels.iter() {
+        commands.entity(level).insert(Respawn);
+    }
+}
+
+/// Briefly freezes gameplay when a potion hits an enemy, to sell the impact.
+/// Only movement and physics stop, so the UI and animations keep the game from looking hung.
+#[derive(Resource)]
+pub struct HitStop {
+    pub timer: Timer,
+}
+
+impl HitStop {
+    pub fn new(seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+        // Nothing has been hit yet
+        timer.tick(timer.duration());
+        Self { timer }
+    }
+
+    /// Starts the freeze over. Hits during a freeze don't add to it, so they can't stack into a long one.
+    pub fn trigger(&mut self) {
+        self.timer.reset();
+    }
+
+    pub fn active(&self) -> bool {
+        !self.timer.finished()
+    }
+}
+
+fn update_hit_stop(mut hit_stop: ResMut<HitStop>, time: Res<Time>, game_state: Res<GameState>) {
+    if *game_state == GameState::Gameplay && hit_stop.active() {
+        hit_stop.timer.tick(time.delta());
+    }
+}
+
+fn freeze_physics(
+    game_state: Res<GameState>,
+    hit_stop: Res<HitStop>,
+    mut rapier: ResMut<RapierConfiguration>,
+) {
+    if game_state.is_changed() || hit_stop.is_changed() {
+        rapier.physics_pipeline_active = *game_state != GameState::Paused && !hit_stop.active();
+    }
+}
+
 #[derive(Component)]
 pub struct WorldCollider;
 
@@ -90,12 +282,93 @@ pub struct WallBundle {
     wall: Wall,
 }
 
+/// Int grid values for hazard tiles.
+const SPIKES: i32 = 2;
+const LAVA: i32 = 3;
+
+/// How a [`Hazard`] hurts the player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HazardDamage {
+    /// A health change, which is negative for damage.
+    Chip(i32),
+    InstantKill,
+}
+
+/// A tile that hurts the player when they touch it, like spikes or lava.
+#[derive(Component)]
+pub struct Hazard {
+    pub damage: HazardDamage,
+}
+
+#[derive(Bundle)]
+pub struct HazardBundle {
+    pub hazard: Hazard,
+    pub sensor: Sensor,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+}
+
+impl LdtkIntCell for HazardBundle {
+    fn bundle_int_cell(int_grid_cell: IntGridCell, layer_instance: &LayerInstance) -> Self {
+        let damage = match int_grid_cell.value {
+            LAVA => HazardDamage::InstantKill,
+            _ => HazardDamage::Chip(-1),
+        };
+        let half_size = layer_instance.grid_size as f32 / 2.;
+
+        Self {
+            hazard: Hazard { damage },
+            sensor: Sensor,
+            collider: Collider::cuboid(half_size, half_size),
+            // Only the player's damage sensor looks for hazards
+            collision_groups: CollisionGroups::new(Group::GROUP_7, Group::GROUP_5),
+        }
+    }
+}
+
+/// Int grid value for one-way platform tiles.
+const ONE_WAY: i32 = 4;
+
+/// A tile that can be jumped up through and landed on, or dropped through by holding crouch.
+/// [`GamePhysicsHooks`] decides which contacts with it count.
+#[derive(Component)]
+pub struct OneWayPlatform;
+
+#[derive(Bundle)]
+pub struct OneWayPlatformBundle {
+    pub platform: OneWayPlatform,
+    pub rigid_body: RigidBody,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_hooks: ActiveHooks,
+}
+
+impl LdtkIntCell for OneWayPlatformBundle {
+    fn bundle_int_cell(_: IntGridCell, layer_instance: &LayerInstance) -> Self {
+        let half_size = layer_instance.grid_size as f32 / 2.;
+
+        Self {
+            platform: OneWayPlatform,
+            rigid_body: RigidBody::Fixed,
+            collider: Collider::cuboid(half_size, half_size),
+            // Potions fly through, but anything that walks can stand on it
+            collision_groups: CollisionGroups::new(
+                Group::GROUP_8,
+                Group::GROUP_2 | Group::GROUP_3 | Group::GROUP_4,
+            ),
+            active_hooks: ActiveHooks::MODIFY_SOLVER_CONTACTS,
+        }
+    }
+}
+
 pub fn spawn_wall_collision(
     mut commands: Commands,
     wall_query: Query<(&GridCoords, &Parent), Added<Wall>>,
     parent_query: Query<&Parent, Without<Wall>>,
     level_query: Query<(Entity, &Handle<LdtkLevel>)>,
     levels: Res<Assets<LdtkLevel>>,
+    mut game_state: ResMut<GameState>,
+    mut error: ResMut<ErrorMessage>,
 ) {
     /// Represents a wide wall that is 1 tile tall
     /// Used to spawn wall collisions
@@ -137,20 +410,25 @@ pub fn spawn_wall_collision(
     if !wall_query.is_empty() {
         level_query.for_each(|(level_entity, level_handle)| {
             if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let level = levels
-                    .get(level_handle)
-                    .expect("Level should be loaded by this point");
+                let Some(level) = levels.get(level_handle) else {
+                    error.report(&mut game_state, "A level wasn't loaded before its walls were spawned.");
+                    return;
+                };
 
-                let LayerInstance {
+                let Some(&LayerInstance {
                     c_wid: width,
                     c_hei: height,
                     grid_size,
                     ..
-                } = level
+                }) = level
                     .level
                     .layer_instances
-                    .clone()
-                    .expect("Level asset should have layers")[0];
+                    .as_ref()
+                    .and_then(|layers| layers.first())
+                else {
+                    error.report(&mut game_state, format!("Level {} doesn't have any layers.", level.level.identifier));
+                    return;
+                };
 
                 // combine wall tiles into flat "plates" in each individual row
                 let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
@@ -255,8 +533,7 @@ pub struct GoldHeartBundle {
     pub collider: Collider,
     pub collision_groups: CollisionGroups,
     pub active_events: ActiveEvents,
-    pub animation_indices: AnimationIndices,
-    pub animation_timer: AnimationTimer,
+    pub animation: AnimationStateMachine,
     pub sprite: TextureAtlasSprite,
     pub texture_atlas: Handle<TextureAtlas>,
 }
@@ -280,18 +557,29 @@ impl LdtkEntity for GoldHeartBundle {
                 collider: Collider::ball(16.0),
                 collision_groups: CollisionGroups { memberships: Group::GROUP_6, filters: Group::GROUP_2 },
                 active_events: ActiveEvents::COLLISION_EVENTS,
-                animation_indices: AnimationIndices { first: 0, last: 3 },
-                animation_timer: AnimationTimer(Timer::from_seconds(1.0 / 4.0, TimerMode::Repeating)),
+                animation: AnimationStateMachine::new("spin", AnimationClip::new(0, 3, 4.0)),
                 sprite: TextureAtlasSprite::default(),
                 texture_atlas,
             }
     }
 }
 
+/// How the last campaign was won, for the win screen to say.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WinReason {
+    #[default]
+    GoldHeart,
+    /// Made it out the bottom of the last level without finding the heart.
+    OutOfLevels,
+}
+
 fn heart_checks(
     mut collision_events: EventReader<CollisionEvent>,
     heart: Query<Entity, With<GoldHeart>>,
     mut game_state: ResMut<GameState>,
+    timer: Res<GameTimer>,
+    mut score: EventWriter<ScoreEvent>,
+    mut reason: ResMut<WinReason>,
 ) {
     let Ok(heart) = heart.get_single() else { return };
     for collision_event in collision_events.iter() {
@@ -300,18 +588,306 @@ fn heart_checks(
 
             if *a == heart || *b == heart {
                 *game_state = GameState::WinScreen;
+                *reason = WinReason::GoldHeart;
+                score.send(ScoreEvent(score::HEART_POINTS));
+                score.send(ScoreEvent(score::time_points(&timer)));
+            }
+        }
+    }
+}
+
+/// Where the player can come back to after dying, instead of starting the run over.
+/// Only kept for the current run.
+#[derive(Resource, Default)]
+pub struct Checkpoint {
+    /// The level index and position in it of the last [`CheckpointFlag`] the player touched.
+    pub last: Option<(usize, Vec2)>,
+    /// Whether the next player to spawn goes to the checkpoint, set when respawning there.
+    pub respawning: bool,
+}
+
+/// Records a [`Checkpoint`] when the player touches it.
+#[derive(Component)]
+pub struct CheckpointFlag;
+
+#[derive(Bundle)]
+pub struct CheckpointBundle {
+    pub flag: CheckpointFlag,
+    pub sensor: Sensor,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub active_events: ActiveEvents,
+    pub sprite: Sprite,
+    pub texture: Handle<Image>,
+}
+
+impl LdtkEntity for CheckpointBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        Self {
+            flag: CheckpointFlag,
+            sensor: Sensor,
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+            // Only the player's body touches it, the same as the gold heart
+            collision_groups: CollisionGroups::new(Group::GROUP_6, Group::GROUP_2),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite {
+                color: CHECKPOINT_INACTIVE,
+                custom_size: Some(size),
+                ..default()
+            },
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+        }
+    }
+}
+
+const CHECKPOINT_INACTIVE: Color = Color::rgba(0.5, 0.5, 0.55, 0.6);
+const CHECKPOINT_ACTIVE: Color = Color::rgba(1., 0.84, 0., 0.8);
+
+fn checkpoint_checks(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut flags: Query<(Entity, &Transform, &mut Sprite), With<CheckpointFlag>>,
+    level_selection: Res<LevelSelection>,
+    mut checkpoint: ResMut<Checkpoint>,
+) {
+    let LevelSelection::Index(level) = *level_selection else { return };
+
+    // Checkpoints only count in their own level, so moving on forgets the last one
+    if checkpoint.last.map_or(false, |(last_level, _)| last_level != level) {
+        checkpoint.last = None;
+    }
+
+    for collision_event in collision_events.iter() {
+        let CollisionEvent::Started(a, b, _) = collision_event else { continue };
+
+        let Some(touched) = [*a, *b].into_iter().find(|entity| flags.contains(*entity)) else { continue };
+
+        // Only the latest checkpoint is lit up
+        for (entity, transform, mut sprite) in flags.iter_mut() {
+            if entity == touched {
+                sprite.color = CHECKPOINT_ACTIVE;
+                checkpoint.last = Some((level, transform.translation.truncate()));
+            } else {
+                sprite.color = CHECKPOINT_INACTIVE;
             }
         }
     }
 }
 
+/// A platform that slides back and forth, carrying the player along when they stand on it.
+#[derive(Component)]
+pub struct MovingPlatform {
+    /// The direction it travels from where it was placed.
+    pub axis: Vec2,
+    /// How far, in pixels, it travels before turning back.
+    pub distance: f32,
+    /// Pixels per second, at the middle of its path.
+    pub speed: f32,
+    elapsed: f32,
+    /// How far it is from where it was placed.
+    offset: Vec2,
+    /// How far it moved this frame, for carrying whatever rides it.
+    pub delta: Vec2,
+}
+
+impl MovingPlatform {
+    /// Seconds for a full trip out and back.
+    fn period(&self) -> f32 {
+        std::f32::consts::PI * self.distance / self.speed.max(1.)
+    }
+}
+
+#[derive(Bundle)]
+pub struct MovingPlatformBundle {
+    pub platform: MovingPlatform,
+    pub rigid_body: RigidBody,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub friction: Friction,
+    pub sprite: Sprite,
+    pub texture: Handle<Image>,
+}
+
+impl LdtkEntity for MovingPlatformBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let fields = &entity_instance.field_instances;
+        let number = |identifier: &str| match field_value(fields, identifier) {
+            Some(FieldValue::Int(Some(value))) => Some(*value as f32),
+            Some(FieldValue::Float(Some(value))) => Some(*value),
+            _ => None,
+        };
+
+        let axis = match field_value(fields, "Axis") {
+            Some(FieldValue::Enum(Some(axis)) | FieldValue::String(Some(axis))) if axis == "Vertical" => Vec2::Y,
+            _ => Vec2::X,
+        };
+
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        Self {
+            platform: MovingPlatform {
+                axis,
+                distance: number("Distance").unwrap_or(64.),
+                speed: number("Speed").unwrap_or(48.),
+                elapsed: 0.,
+                offset: Vec2::ZERO,
+                delta: Vec2::ZERO,
+            },
+            rigid_body: RigidBody::KinematicPositionBased,
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+            // Solid to everything, the same as walls
+            collision_groups: CollisionGroups::new(Group::GROUP_1, Group::all() & !Group::GROUP_1),
+            friction: Friction::new(0.5),
+            sprite: Sprite {
+                color: Color::rgb(0.35, 0.3, 0.25),
+                custom_size: Some(size),
+                ..default()
+            },
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+        }
+    }
+}
+
+/// A region the player can climb up and down while they're inside it.
+#[derive(Component)]
+pub struct Ladder;
+
+#[derive(Bundle)]
+pub struct LadderBundle {
+    pub ladder: Ladder,
+    pub sensor: Sensor,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub sprite: Sprite,
+    pub texture: Handle<Image>,
+}
+
+impl LdtkEntity for LadderBundle {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        _: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        Self {
+            ladder: Ladder,
+            sensor: Sensor,
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+            // Only the player's body climbs, so the ground sensors never see ladders
+            collision_groups: CollisionGroups::new(Group::GROUP_9, Group::GROUP_2),
+            sprite: Sprite {
+                color: Color::rgba(0.45, 0.3, 0.15, 0.8),
+                custom_size: Some(size),
+                ..default()
+            },
+            texture: DEFAULT_IMAGE_HANDLE.typed(),
+        }
+    }
+}
+
+fn move_platforms(
+    mut platforms: Query<(&mut MovingPlatform, &mut Transform)>,
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    hit_stop: Res<HitStop>,
+) {
+    let frozen = *game_state != GameState::Gameplay || hit_stop.active();
+
+    for (mut platform, mut transform) in platforms.iter_mut() {
+        if frozen {
+            platform.delta = Vec2::ZERO;
+            continue;
+        }
+
+        platform.elapsed += time.delta_seconds();
+
+        // Eases in and out at each end, rather than bouncing off them
+        let phase = platform.elapsed / platform.period() * std::f32::consts::TAU;
+        let offset = platform.axis * platform.distance * 0.5 * (1. - phase.cos());
+
+        platform.delta = offset - platform.offset;
+        platform.offset = offset;
+        transform.translation += platform.delta.extend(0.);
+    }
+}
+
+/// Moves the player along with the platform they're standing on, so they don't slide off it.
+fn carry_riders(
+    platforms: Query<(Entity, &MovingPlatform)>,
+    ground_sensor: Query<Entity, With<PlayerGroundSensor>>,
+    mut player: Query<(&mut Transform, &Velocity), With<Player>>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let Ok(ground_sensor) = ground_sensor.get_single() else { return };
+    let Ok((mut transform, velocity)) = player.get_single_mut() else { return };
+
+    for (entity, platform) in platforms.iter() {
+        if rapier_context.intersection_pair(ground_sensor, entity) != Some(true) {
+            continue;
+        }
+
+        // A player jumping off is still touching the platform for a frame,
+        // and carrying them then would eat into the jump
+        let platform_speed = platform.delta.y / time.delta_seconds().max(f32::EPSILON);
+        if velocity.linvel.y > platform_speed + 1. {
+            continue;
+        }
+
+        transform.translation += platform.delta.extend(0.);
+        break;
+    }
+}
+
 #[derive(SystemParam)]
 struct GamePhysicsHooks<'w, 's> {
     world_colliders: Query<'w, 's, &'static WorldCollider>,
+    one_way_platforms: Query<'w, 's, (), With<OneWayPlatform>>,
+    velocities: Query<'w, 's, &'static Velocity>,
+    players: Query<'w, 's, &'static PlayerPhysics, With<Player>>,
+    input: Res<'w, PlayerInput>,
 }
 
 impl BevyPhysicsHooks for GamePhysicsHooks<'_, '_> {
     fn modify_solver_contacts(&self, context: ContactModificationContextView) {
+        let platform_first = self.one_way_platforms.contains(context.collider1());
+        if platform_first || self.one_way_platforms.contains(context.collider2()) {
+            let other = if platform_first { context.collider2() } else { context.collider1() };
+            // The normal points from the first collider to the second, so flip it to point away from the platform
+            let up = if platform_first { context.raw.normal.y } else { -context.raw.normal.y };
+            let rising = self.velocities.get(other).map_or(false, |velocity| velocity.linvel.y > 0.);
+            // Climbing a ladder goes straight through, the same as dropping down
+            let dropping = self
+                .players
+                .get(other)
+                .map_or(false, |physics| self.input.crouch_held || physics.climbing);
+
+            // Only landing on top counts, so the sides and bottom don't block anything
+            if up < 0.5 || rising || dropping {
+                context.raw.solver_contacts.clear();
+            }
+            return;
+        }
+
         if !self.world_colliders.contains(context.collider1())
             && !self.world_colliders.contains(context.collider2())
         {